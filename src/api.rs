@@ -0,0 +1,223 @@
+//! The library API: running the analysis from Rust.
+//!
+//! [`Udeps`] takes the options of the command line, runs the same analysis as `cargo udeps`
+//! and returns a [`Report`] with a verdict for each direct dependency of the workspace members.
+//! The `cargo-udeps` configuration of the workspace (ignore lists, severities and
+//! configuration files) applies like it does on the command line.
+
+use std::path::PathBuf;
+
+use cargo::core::dependency::DepKind;
+use cargo::util::context::GlobalContext;
+use cargo::{CargoResult, CliError};
+
+use crate::{AnalysisOptions, Backend, DependencyStatus, Outcome, Severity};
+
+/// A builder for running the analysis.
+///
+/// ```no_run
+/// let report = cargo_udeps::Udeps::new()
+///     .manifest_path("path/to/Cargo.toml")
+///     .workspace()
+///     .all_targets()
+///     .run()?;
+/// for dependency in report.unused() {
+///     println!("{} has an unused dependency `{}`", dependency.member, dependency.name_in_toml);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Udeps {
+	options :AnalysisOptions,
+}
+
+impl Udeps {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The `Cargo.toml` of the workspace, the one in the current directory by default.
+	pub fn manifest_path(mut self, manifest_path :impl Into<PathBuf>) -> Self {
+		self.options.manifest_path = Some(manifest_path.into());
+		self
+	}
+
+	/// Checks the given package, like `--package`. Can be called several times.
+	pub fn package(mut self, spec :impl Into<String>) -> Self {
+		self.options.packages.push(spec.into());
+		self
+	}
+
+	/// Checks all packages in the workspace, like `--workspace`.
+	pub fn workspace(mut self) -> Self {
+		self.options.workspace = true;
+		self
+	}
+
+	/// Excludes the given package when checking the workspace, like `--exclude`.
+	pub fn exclude(mut self, spec :impl Into<String>) -> Self {
+		self.options.exclude.push(spec.into());
+		self
+	}
+
+	/// Activates the given feature, like `--features`. Can be called several times.
+	pub fn feature(mut self, feature :impl Into<String>) -> Self {
+		self.options.features.get_or_insert_with(Vec::new).push(feature.into());
+		self
+	}
+
+	pub fn all_features(mut self) -> Self {
+		self.options.all_features = true;
+		self
+	}
+
+	pub fn no_default_features(mut self) -> Self {
+		self.options.no_default_features = true;
+		self
+	}
+
+	/// Checks for the given target triple, like `--target`.
+	pub fn target(mut self, triple :impl Into<String>) -> Self {
+		self.options.target = Some(triple.into());
+		self
+	}
+
+	/// Checks all targets, like `--all-targets`.
+	pub fn all_targets(mut self) -> Self {
+		self.options.all_targets = true;
+		self
+	}
+
+	pub fn backend(mut self, backend :Backend) -> Self {
+		self.options.backend = Some(backend);
+		self
+	}
+
+	/// The program for [`Backend::External`], like `--backend-command`.
+	pub fn backend_command(mut self, program :impl Into<PathBuf>) -> Self {
+		self.options.backend_command = Some(program.into());
+		self
+	}
+
+	/// Runs the analysis with a default `GlobalContext`.
+	pub fn run(&self) -> CargoResult<Report> {
+		self.run_with_context(&mut GlobalContext::default()?)
+	}
+
+	/// Runs the analysis with the given `GlobalContext`, whose shell gets the progress and warnings.
+	pub fn run_with_context(&self, gctx :&mut GlobalContext) -> CargoResult<Report> {
+		let outcome = self.options.outcome(gctx, None).map_err(cli_error)?;
+		Ok(Report::new(&outcome))
+	}
+}
+
+pub(crate) fn cli_error(err :CliError) -> anyhow::Error {
+	err.error.unwrap_or_else(|| anyhow::anyhow!("exited with code {}", err.exit_code))
+}
+
+/// The result of the analysis.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Report {
+	/// Whether there were no findings with the `deny` severity
+	pub success :bool,
+	/// The direct dependencies of the workspace members, sorted by member, kind and name
+	pub dependencies :Vec<DependencyReport>,
+}
+
+impl Report {
+	fn new(outcome :&Outcome) -> Self {
+		let dependencies = outcome
+			.declared_dependencies()
+			.into_iter()
+			.map(|((id, kind, name_in_toml), dep)| {
+				let verdict = match dep.status {
+					DependencyStatus::Used => DependencyVerdict::Used,
+					DependencyStatus::DeclaredUsed => DependencyVerdict::DeclaredUsed,
+					DependencyStatus::Unused | DependencyStatus::Baselined | DependencyStatus::Allowed => DependencyVerdict::Unused {
+						severity : outcome.severity.unused(kind),
+					},
+					DependencyStatus::Ignored => DependencyVerdict::Ignored {
+						reason : outcome.ignored_deps
+							.get(&id)
+							.and_then(|deps| deps.ignored_deps(kind).get(&name_in_toml).cloned())
+							.flatten(),
+					},
+					DependencyStatus::NotEvaluated => DependencyVerdict::NotEvaluated,
+				};
+				DependencyReport {
+					member : id.name().to_string(),
+					member_version : id.version().to_string(),
+					manifest_path : outcome.packages[&id].manifest_path().to_owned(),
+					name_in_toml : name_in_toml.to_string(),
+					package_name : dep.package_name.to_string(),
+					kind : kind.into(),
+					verdict,
+					used_by : outcome.used_in
+						.get(&(id, kind, name_in_toml))
+						.map(|targets| targets.iter().cloned().collect())
+						.unwrap_or_default(),
+				}
+			})
+			.collect();
+		Self { success : outcome.success, dependencies }
+	}
+
+	/// The dependencies that were found to be unused, whatever their severity.
+	pub fn unused(&self) -> impl Iterator<Item = &DependencyReport> {
+		self.dependencies
+			.iter()
+			.filter(|dep| matches!(dep.verdict, DependencyVerdict::Unused { .. }))
+	}
+}
+
+/// A direct dependency of a workspace member.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct DependencyReport {
+	/// The name of the workspace member
+	pub member :String,
+	pub member_version :String,
+	/// The `Cargo.toml` of the workspace member
+	pub manifest_path :PathBuf,
+	/// The name of the dependency in the manifest
+	pub name_in_toml :String,
+	/// The name of the package on the registry, which differs from `name_in_toml` for renamed dependencies
+	pub package_name :String,
+	pub kind :DependencyKind,
+	pub verdict :DependencyVerdict,
+	/// The targets that used the dependency, like `lib:foo`
+	pub used_by :Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DependencyKind {
+	Normal,
+	Development,
+	Build,
+}
+
+impl From<DepKind> for DependencyKind {
+	fn from(kind :DepKind) -> Self {
+		match kind {
+			DepKind::Normal => Self::Normal,
+			DepKind::Development => Self::Development,
+			DepKind::Build => Self::Build,
+		}
+	}
+}
+
+/// What the analysis found out about a dependency.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DependencyVerdict {
+	Used,
+	/// Marked as used in the source with `#[cfg(cargo_udeps)]`
+	DeclaredUsed,
+	/// Not used by any of the checked targets
+	Unused { severity :Severity },
+	/// Unused, but ignored by the configuration
+	Ignored { reason :Option<String> },
+	/// Not passed to any compilation, for example because it is for another platform
+	NotEvaluated,
+}
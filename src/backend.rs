@@ -0,0 +1,218 @@
+//! Backends find out which crates a compilation used.
+//!
+//! `depinfo` reads the dependency info files that rustc writes. `external` asks a user-supplied
+//! program, which gets a JSON request on stdin for each compilation of a workspace member
+//! and answers with JSON on stdout, so that other usage detectors can be tried out.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::Context;
+use cargo::core::package_id::PackageId;
+use cargo::core::shell::Shell;
+use cargo::CargoResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{Backend, CmdInfo, ShellExt};
+
+/// Version of the protocol of the `external` backend.
+const PROTOCOL_VERSION :u32 = 1;
+
+pub(crate) trait AnalysisBackend {
+	/// The crates the compilation of `cmd_info` used, and its source files.
+	fn analyze(&self, cmd_info :&CmdInfo, artifacts :&Artifacts<'_>, shell :&mut Shell) -> CargoResult<BackendOutput>;
+}
+
+/// Creates the backend. The program of the `external` backend runs in `cwd`,
+/// and relative paths to it are resolved against `cwd` too.
+pub(crate) fn new(backend :Backend, command :Option<&Path>, cwd :&Path) -> CargoResult<Box<dyn AnalysisBackend>> {
+	match (backend, command) {
+		(Backend::Depinfo, _) => Ok(Box::new(DepinfoBackend)),
+		(Backend::External, Some(program)) => {
+			// A bare name is looked up in `PATH`
+			let program = if program.components().count() > 1 {
+				cwd.join(program)
+			} else {
+				program.to_owned()
+			};
+			Ok(Box::new(ExternalBackend { program, cwd : cwd.to_owned() }))
+		},
+		(Backend::External, None) => Err(anyhow::anyhow!("the `external` backend needs `--backend-command`")),
+	}
+}
+
+/// The artifacts of all compilations of this run.
+pub(crate) struct Artifacts<'a> {
+	/// The packages by the base names of their artifacts, like `libfoo-0123456789abcdef`
+	pub(crate) by_base_name :&'a HashMap<String, PackageId>,
+}
+
+#[derive(Default)]
+pub(crate) struct BackendOutput {
+	pub(crate) used :Vec<UsedCrate>,
+	/// The source files of the compilation, which are searched for `#[cfg(cargo_udeps)]` markers
+	pub(crate) sources :Vec<PathBuf>,
+}
+
+/// A crate a compilation used.
+pub(crate) enum UsedCrate {
+	Package(PackageId),
+	/// The snake-cased name of a library, for artifacts not built in this run
+	LibName(String),
+	/// The name the crate was passed to rustc with, in `--extern`
+	ExternCrateName(String),
+}
+
+struct DepinfoBackend;
+
+impl AnalysisBackend for DepinfoBackend {
+	fn analyze(&self, cmd_info :&CmdInfo, artifacts :&Artifacts<'_>, shell :&mut Shell) -> CargoResult<BackendOutput> {
+		let mut output = BackendOutput::default();
+		for dep in cmd_info.get_depinfo(shell)?.deps_of_depfile() {
+			if dep.extension() == Some("rs".as_ref()) {
+				output.sources.push(dep);
+				continue;
+			}
+			let Some(fs) = dep.file_stem().and_then(|fs| fs.to_str()) else {
+				continue;
+			};
+			// The file names are like cratename-hash.rmeta or .rlib,
+			// where "hash" is a hash string that cargo calls "metadata"
+			// internally and computes in its "compute_metadata" function,
+			// and cratename is the snakecased crate name.
+
+			// First, we continue if there is no - in the filename.
+			// it's likely a source file or some other artifact we aren't
+			// interested in. This is obviously only a stupid heuristic.
+			let Some((lib_name, _)) = fs.split_once('-') else {
+				continue;
+			};
+
+			// The metadata hash is not available through cargo's api
+			// outside of the Executor trait impl. We do our best to obtain
+			// the hashes from that impl, but the executor is not called
+			// for anything but crates that have to be recompiled.
+			// Thus, any crates that weren't recompiled we don't know the
+			// metadata hash of. So we perform a check: if we know the metadata
+			// hash, we use it, otherwise we don't.
+			// This gives a bit surprising behaviour when re-running
+			// cargo-udeps but at least sometimes the results are more accurate.
+			match artifacts.by_base_name.get(fs) {
+				Some(&pkg_id) => output.used.push(UsedCrate::Package(pkg_id)),
+				None => {
+					// TODO this is a hack as we unconditionally strip the prefix.
+					// It won't work for proc macro crates that start with "lib".
+					// See maybe_lib in the code above.
+					let lib_name = lib_name.strip_prefix("lib").unwrap_or(lib_name);
+					output.used.push(UsedCrate::LibName(lib_name.to_owned()));
+				},
+			}
+		}
+		Ok(output)
+	}
+}
+
+struct ExternalBackend {
+	program :PathBuf,
+	cwd :PathBuf,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+	version :u32,
+	/// A package ID specification
+	package_id :String,
+	target_kind :&'static str,
+	target_name :&'a str,
+	crate_name :&'a str,
+	crate_type :&'a str,
+	out_dir :&'a str,
+	depinfo_path :PathBuf,
+	extern_crate_names :Vec<&'a str>,
+	/// The package ID specifications by artifact base name
+	artifacts :BTreeMap<&'a str, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Response {
+	used :Vec<ResponseUsedCrate>,
+	#[serde(default)]
+	sources :Vec<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum ResponseUsedCrate {
+	PackageId(String),
+	LibName(String),
+	ExternCrateName(String),
+}
+
+impl AnalysisBackend for ExternalBackend {
+	fn analyze(&self, cmd_info :&CmdInfo, artifacts :&Artifacts<'_>, shell :&mut Shell) -> CargoResult<BackendOutput> {
+		let mut extern_crate_names = cmd_info.extern_crate_names.iter().map(String::as_str).collect::<Vec<_>>();
+		extern_crate_names.sort_unstable();
+		let request = Request {
+			version : PROTOCOL_VERSION,
+			package_id : cmd_info.pkg.to_spec().to_string(),
+			target_kind : cmd_info.target_kind,
+			target_name : &cmd_info.target_name,
+			crate_name : &cmd_info.crate_name,
+			crate_type : &cmd_info.crate_type,
+			out_dir : &cmd_info.out_dir,
+			depinfo_path : cmd_info.get_depinfo_path(),
+			extern_crate_names,
+			artifacts : artifacts.by_base_name
+				.iter()
+				.map(|(base_name, id)| (&**base_name, id.to_spec().to_string()))
+				.collect(),
+		};
+		let request = serde_json::to_string(&request).expect("should not fail");
+
+		shell.info(format_args!("Running `{}` for `{}`", self.program.display(), cmd_info.target_name))?;
+		let mut child = Command::new(&self.program)
+			.current_dir(&self.cwd)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.with_context(|| format!("could not run the backend `{}`", self.program.display()))?;
+		// The program may exit without reading the request, its exit status tells what happened then
+		match child.stdin.take().expect("piped").write_all(request.as_bytes()) {
+			Err(err) if err.kind() != io::ErrorKind::BrokenPipe => return Err(err.into()),
+			_ => {},
+		}
+		let output = child.wait_with_output()?;
+		if !output.status.success() {
+			anyhow::bail!(
+				"the backend `{}` failed with {}: {}",
+				self.program.display(),
+				output.status,
+				String::from_utf8_lossy(&output.stderr).trim(),
+			);
+		}
+		let response = serde_json::from_slice::<Response>(&output.stdout)
+			.with_context(|| format!("could not parse the response of the backend `{}`", self.program.display()))?;
+
+		let by_spec = artifacts.by_base_name
+			.values()
+			.chain(std::iter::once(&cmd_info.pkg))
+			.map(|&id| (id.to_spec().to_string(), id))
+			.collect::<HashMap<_, _>>();
+		let used = response.used
+			.into_iter()
+			.map(|used| Ok(match used {
+				ResponseUsedCrate::PackageId(spec) => match by_spec.get(&spec) {
+					Some(&id) => UsedCrate::Package(id),
+					None => anyhow::bail!("the backend `{}` returned the unknown package `{}`", self.program.display(), spec),
+				},
+				ResponseUsedCrate::LibName(name) => UsedCrate::LibName(name),
+				ResponseUsedCrate::ExternCrateName(name) => UsedCrate::ExternCrateName(name),
+			}))
+			.collect::<CargoResult<_>>()?;
+		Ok(BackendOutput { used, sources : response.sources })
+	}
+}
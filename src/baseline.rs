@@ -0,0 +1,152 @@
+//! `--baseline` and `--write-baseline`: accepting the findings of a point in time.
+//!
+//! Entries are keyed by package name rather than `PackageId`, which contains the path
+//! of the workspace, so that a baseline can be checked in and used on other machines.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use cargo::core::dependency::DepKind;
+use cargo::core::package_id::PackageId;
+use cargo::CargoResult;
+use serde::{Deserialize, Serialize};
+
+use crate::{Outcome, OutcomeUnusedDeps};
+
+const VERSION :u32 = 1;
+
+const KINDS :[DepKind; 3] = [DepKind::Normal, DepKind::Development, DepKind::Build];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct Baseline {
+	version :u32,
+	#[serde(default)]
+	unused :BTreeMap<String, BaselineDeps>,
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	declared_used :BTreeMap<String, BaselineDeps>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BaselineDeps {
+	#[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+	normal :BTreeSet<String>,
+	#[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+	development :BTreeSet<String>,
+	#[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+	build :BTreeSet<String>,
+}
+
+impl BaselineDeps {
+	fn deps(&self, kind :DepKind) -> &BTreeSet<String> {
+		match kind {
+			DepKind::Normal => &self.normal,
+			DepKind::Development => &self.development,
+			DepKind::Build => &self.build,
+		}
+	}
+
+	fn deps_mut(&mut self, kind :DepKind) -> &mut BTreeSet<String> {
+		match kind {
+			DepKind::Normal => &mut self.normal,
+			DepKind::Development => &mut self.development,
+			DepKind::Build => &mut self.build,
+		}
+	}
+}
+
+impl Baseline {
+	pub(crate) fn load(path :&Path) -> CargoResult<Self> {
+		let json = fs::read_to_string(path)
+			.with_context(|| format!("could not read the baseline `{}`", path.display()))?;
+		let baseline = serde_json::from_str::<Self>(&json)
+			.with_context(|| format!("could not parse the baseline `{}`", path.display()))?;
+		if baseline.version != VERSION {
+			anyhow::bail!(
+				"the baseline `{}` has version {}, but only version {} is supported",
+				path.display(),
+				baseline.version,
+				VERSION,
+			);
+		}
+		Ok(baseline)
+	}
+
+	/// The unused and declared used dependencies of `outcome`.
+	pub(crate) fn new(outcome :&Outcome) -> Self {
+		let from = |deps_by_member :&BTreeMap<PackageId, OutcomeUnusedDeps>| {
+			let mut baseline = BTreeMap::<_, BaselineDeps>::new();
+			for (id, deps) in deps_by_member {
+				let entry = baseline.entry(id.name().to_string()).or_default();
+				for kind in KINDS {
+					entry.deps_mut(kind).extend(deps.unused_deps(kind).iter().map(|dep| dep.to_string()));
+				}
+			}
+			baseline
+		};
+		Self {
+			version : VERSION,
+			unused : from(&outcome.unused_deps),
+			declared_used : from(&outcome.declared_used_deps),
+		}
+	}
+
+	pub(crate) fn write(&self, path :&Path) -> CargoResult<()> {
+		let mut json = serde_json::to_string_pretty(self).expect("should not fail");
+		json.push('\n');
+		fs::write(path, json)
+			.with_context(|| format!("could not write the baseline `{}`", path.display()))
+	}
+
+	/// Moves the findings in the baseline to `outcome.baselined_deps`,
+	/// and records the entries that are no longer found in `outcome.fixed_baseline`.
+	///
+	/// Entries of packages that aren't in `analyzed` are left alone.
+	pub(crate) fn apply(&self, outcome :&mut Outcome, analyzed :&HashSet<PackageId>) -> CargoResult<()> {
+		let mut members = BTreeMap::<_, Vec<_>>::new();
+		for (&id, package) in outcome.packages.iter().filter(|(id, _)| analyzed.contains(id)) {
+			members.entry(id.name().to_string()).or_default().push((id, package.manifest_path().to_owned()));
+		}
+		for ids in members.values_mut() {
+			ids.sort();
+		}
+
+		for (baseline, findings) in [
+			(&self.unused, &mut outcome.unused_deps),
+			(&self.declared_used, &mut outcome.declared_used_deps),
+		] {
+			for (name, baseline_deps) in baseline {
+				let Some(ids) = members.get(name) else {
+					continue;
+				};
+				for kind in KINDS {
+					for dep in baseline_deps.deps(kind) {
+						let found = ids.iter().find(|(id, _)| {
+							findings.get(id).is_some_and(|deps| deps.unused_deps(kind).iter().any(|d| **d == **dep))
+						});
+						let (id, manifest_path) = found.unwrap_or(&ids[0]);
+						let moved_to = if found.is_some() {
+							let deps = findings.get_mut(id).expect("should be found");
+							let dep = *deps.unused_deps(kind).iter().find(|d| ***d == **dep).expect("should be found");
+							deps.unused_deps_mut(kind).remove(&dep);
+							deps.evaluated_in.values_mut().for_each(|deps| { deps.remove(&dep); });
+							deps.evaluated_in.retain(|_, deps| !deps.is_empty());
+							&mut outcome.baselined_deps
+						} else {
+							&mut outcome.fixed_baseline
+						};
+						if !moved_to.contains_key(id) {
+							moved_to.insert(*id, OutcomeUnusedDeps::new(manifest_path)?);
+						}
+						moved_to.get_mut(id).expect("just inserted").unused_deps_mut(kind).insert(dep.as_str().into());
+					}
+				}
+			}
+			findings.retain(|_, deps| deps.any_kind(|_| true));
+		}
+		Ok(())
+	}
+}
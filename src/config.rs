@@ -0,0 +1,284 @@
+//! Support for `udeps.toml` configuration files.
+//!
+//! A config file is looked up as `udeps.toml` or `.cargo/udeps.toml` in every directory
+//! from each workspace member's one up to the workspace root, so that `crates/udeps.toml`
+//! applies to all members in `crates`.
+//!
+//! The ignore lists of all these files apply to the member. Options and severities come
+//! from the files from the current directory up to the workspace root, like cargo's own
+//! configuration: nearer files take precedence over the ones further up.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use cargo::core::{Package, Workspace};
+use cargo::CargoResult;
+use serde::Deserialize;
+
+use crate::{Backend, OutputKind, PackageMetadataCargoUdepsIgnore, Severities, Severity};
+
+pub(crate) const FILE_NAMES :&[&str] = &["udeps.toml", ".cargo/udeps.toml"];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ConfigFile {
+	#[serde(flatten)]
+	options: ConfigOptions,
+	#[serde(default)]
+	configs: BTreeMap<String, ConfigOptions>,
+	#[serde(default)]
+	severity: SeverityPolicy,
+	#[serde(default)]
+	ignore: PackageMetadataCargoUdepsIgnore,
+	#[serde(default)]
+	package: BTreeMap<String, ConfigFilePackage>,
+	/// `deny_unknown_fields` doesn't work together with `flatten`.
+	#[serde(flatten)]
+	unknown: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFilePackage {
+	#[serde(default)]
+	ignore: PackageMetadataCargoUdepsIgnore,
+}
+
+/// Defaults for command line options. Options given on the command line take precedence.
+///
+/// Used by config files and `workspace.metadata.cargo-udeps`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct ConfigOptions {
+	pub(crate) all_targets :Option<bool>,
+	pub(crate) features :Option<Vec<String>>,
+	pub(crate) all_features :Option<bool>,
+	pub(crate) no_default_features :Option<bool>,
+	pub(crate) profile :Option<String>,
+	pub(crate) target :Option<StringOrVec>,
+	pub(crate) backend :Option<Backend>,
+	pub(crate) output :Option<OutputKind>,
+}
+
+impl ConfigOptions {
+	/// Fills the options missing from `self` with the ones from `fallback`.
+	pub(crate) fn or(self, fallback :Self) -> Self {
+		Self {
+			all_targets : self.all_targets.or(fallback.all_targets),
+			features : self.features.or(fallback.features),
+			all_features : self.all_features.or(fallback.all_features),
+			no_default_features : self.no_default_features.or(fallback.no_default_features),
+			profile : self.profile.or(fallback.profile),
+			target : self.target.or(fallback.target),
+			backend : self.backend.or(fallback.backend),
+			output : self.output.or(fallback.output),
+		}
+	}
+}
+
+/// How findings are reported, by dependency kind and finding category.
+///
+/// Used by config files, `workspace.metadata.cargo-udeps` and `--severity`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub(crate) struct SeverityPolicy {
+	/// Unused dependencies of any kind, unless overridden by the kind's entry
+	unused :Option<Severity>,
+	normal :Option<Severity>,
+	development :Option<Severity>,
+	build :Option<Severity>,
+	declared_used :Option<Severity>,
+}
+
+impl SeverityPolicy {
+	pub(crate) const CATEGORIES :&'static [&'static str] = &["unused", "normal", "development", "build", "declared-used"];
+
+	pub(crate) fn set(&mut self, category :&str, severity :Severity) {
+		let field = match category {
+			"unused" => &mut self.unused,
+			"normal" => &mut self.normal,
+			"development" => &mut self.development,
+			"build" => &mut self.build,
+			"declared-used" => &mut self.declared_used,
+			_ => unreachable!("checked by the command line parser"),
+		};
+		*field = Some(severity);
+	}
+
+	/// Fills the entries missing from `self` with the ones from `fallback`.
+	///
+	/// An `unused` entry in `self` takes precedence over the kinds in `fallback`.
+	pub(crate) fn or(self, fallback :Self) -> Self {
+		let kind = |own :Option<Severity>, fallback_kind :Option<Severity>| {
+			own.or(self.unused).or(fallback_kind)
+		};
+		Self {
+			normal : kind(self.normal, fallback.normal),
+			development : kind(self.development, fallback.development),
+			build : kind(self.build, fallback.build),
+			unused : self.unused.or(fallback.unused),
+			declared_used : self.declared_used.or(fallback.declared_used),
+		}
+	}
+
+	pub(crate) fn resolve(self) -> Severities {
+		let unused = |kind :Option<Severity>| kind.or(self.unused).unwrap_or(Severity::Deny);
+		Severities {
+			normal : unused(self.normal),
+			development : unused(self.development),
+			build : unused(self.build),
+			declared_used : self.declared_used.unwrap_or(Severity::Warn),
+		}
+	}
+}
+
+/// Like cargo's `build.target`, which is either a single triple or a list of them.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum StringOrVec {
+	String(String),
+	Vec(Vec<String>),
+}
+
+impl StringOrVec {
+	pub(crate) fn into_vec(self) -> Vec<String> {
+		match self {
+			StringOrVec::String(s) => vec![s],
+			StringOrVec::Vec(v) => v,
+		}
+	}
+}
+
+impl ConfigFile {
+	/// Loads the config file in `dir`, if there is one.
+	fn load(dir :&Path, ws :&Workspace<'_>) -> CargoResult<Option<(PathBuf, Self)>> {
+		let mut paths = FILE_NAMES.iter().map(|name| dir.join(name)).filter(|path| path.is_file());
+		let Some(path) = paths.next() else {
+			return Ok(None);
+		};
+		if let Some(other) = paths.next() {
+			ws.gctx().shell().warn(format!(
+				"both `{}` and `{}` exist, ignoring the latter",
+				path.display(),
+				other.display(),
+			))?;
+		}
+		let content = fs::read_to_string(&path)
+			.with_context(|| format!("could not read `{}`", path.display()))?;
+		let file = toml::from_str::<Self>(&content)
+			.with_context(|| format!("could not parse `{}`", path.display()))?;
+		if let Some(key) = file.unknown.keys().next() {
+			return Err(anyhow::anyhow!("unknown key `{}` in `{}`", key, path.display()));
+		}
+		Ok(Some((path, file)))
+	}
+
+	/// Warns about ignore entries for dependencies that aren't declared by the packages they apply to.
+	fn warn_undeclared(&self, path :&Path, packages :&[&Package], ws :&Workspace<'_>) -> CargoResult<()> {
+		let mut shell = ws.gctx().shell();
+		self.ignore.warn_undeclared(&format!("`{}`", path.display()), packages, &mut shell)?;
+		for (name, section) in &self.package {
+			let packages = packages
+				.iter()
+				.copied()
+				.filter(|package| package.name() == name.as_str())
+				.collect::<Vec<_>>();
+			if packages.is_empty() {
+				shell.warn(format!(
+					"`{}` has a section for `{}`, but there is no such workspace member",
+					path.display(),
+					name,
+				))?;
+				continue;
+			}
+			let source = format!("`package.{}` in `{}`", name, path.display());
+			section.ignore.warn_undeclared(&source, &packages, &mut shell)?;
+		}
+		Ok(())
+	}
+}
+
+/// The config files of a workspace.
+#[derive(Debug, Default)]
+pub(crate) struct Config {
+	/// The config file of each directory that has one
+	files :HashMap<PathBuf, ConfigFile>,
+	/// The directories from the current one up to the workspace root, whose files set the options
+	current_dirs :Vec<PathBuf>,
+}
+
+impl Config {
+	pub(crate) fn load(ws :&Workspace<'_>) -> CargoResult<Self> {
+		let mut members_by_dir = BTreeMap::<_, Vec<_>>::new();
+		for member in ws.members() {
+			for dir in dirs(member.root(), ws.root()) {
+				members_by_dir.entry(dir).or_default().push(member);
+			}
+		}
+		let current_dirs = dirs(ws.gctx().cwd(), ws.root());
+		for dir in &current_dirs {
+			members_by_dir.entry(dir.clone()).or_default();
+		}
+		let mut files = HashMap::new();
+		for (dir, members) in members_by_dir {
+			if let Some((path, file)) = ConfigFile::load(&dir, ws)? {
+				file.warn_undeclared(&path, &members, ws)?;
+				files.insert(dir, file);
+			}
+		}
+		Ok(Self { files, current_dirs })
+	}
+
+	/// The config files that set the options, the nearest first.
+	fn current_files(&self) -> impl Iterator<Item = &ConfigFile> {
+		self.current_dirs.iter().filter_map(|dir| self.files.get(dir))
+	}
+
+	pub(crate) fn options(&self) -> ConfigOptions {
+		self.current_files()
+			.fold(ConfigOptions::default(), |options, file| options.or(file.options.clone()))
+	}
+
+	pub(crate) fn severity(&self) -> SeverityPolicy {
+		self.current_files()
+			.fold(SeverityPolicy::default(), |severity, file| severity.or(file.severity.clone()))
+	}
+
+	/// The named configurations. Each one is merged with the ones of the same name further up.
+	pub(crate) fn configs(&self) -> BTreeMap<String, ConfigOptions> {
+		let mut configs = BTreeMap::<String, ConfigOptions>::new();
+		for file in self.current_files() {
+			for (name, options) in &file.configs {
+				let merged = configs.remove(name).unwrap_or_default().or(options.clone());
+				configs.insert(name.clone(), merged);
+			}
+		}
+		configs
+	}
+
+	/// The ignore lists of the config files that apply to `pkg`.
+	pub(crate) fn ignores<'a>(&'a self, pkg :&Package, ws :&Workspace<'_>) -> impl Iterator<Item = &'a PackageMetadataCargoUdepsIgnore> + 'a {
+		let name = pkg.name();
+		dirs(pkg.root(), ws.root())
+			.into_iter()
+			.filter_map(|dir| self.files.get(&dir))
+			.flat_map(move |file| {
+				std::iter::once(&file.ignore).chain(file.package.get(&*name).map(|p| &p.ignore))
+			})
+	}
+}
+
+/// The directories from `dir` up to the workspace `root`, or only `root` if `dir` is outside of it.
+pub(crate) fn dirs(dir :&Path, root :&Path) -> Vec<PathBuf> {
+	let mut dirs = dir
+		.ancestors()
+		.take_while(|dir| dir.starts_with(root))
+		.map(Path::to_owned)
+		.collect::<Vec<_>>();
+	if dirs.is_empty() {
+		dirs.push(root.to_owned());
+	}
+	dirs
+}
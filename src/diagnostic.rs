@@ -0,0 +1,253 @@
+//! Diagnostics pointing into `Cargo.toml`: rustc-style text with `--output diagnostic`,
+//! and rustc's JSON format with `--output rustc-json`.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use cargo::core::dependency::DepKind;
+use cargo::core::package_id::PackageId;
+use nu_ansi_term::{Color, Style};
+use serde_json::{json, Value};
+
+use crate::manifest::{Manifest, ManifestDependency};
+use crate::{Outcome, Severity};
+
+const KINDS :&[(DepKind, &str)] = &[
+	(DepKind::Normal, "dependency"),
+	(DepKind::Development, "dev-dependency"),
+	(DepKind::Build, "build-dependency"),
+];
+
+/// How the `rendered` field of JSON messages looks, after cargo's `--message-format json-diagnostic-*`.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Rendering {
+	/// Only the location, level and message on one line
+	pub(crate) short :bool,
+	/// With ANSI colors
+	pub(crate) ansi :bool,
+}
+
+pub(crate) struct Finding<'a> {
+	pub(crate) level :&'static str,
+	pub(crate) message :String,
+	id :PackageId,
+	/// Relative to the workspace root if possible
+	pub(crate) manifest_path :&'a Path,
+	pub(crate) manifest :Option<&'a Manifest>,
+	pub(crate) entry :Option<&'a ManifestDependency>,
+	/// Whether to suggest removing the entry
+	pub(crate) unused :bool,
+}
+
+pub(crate) fn findings(outcome :&Outcome) -> Vec<Finding<'_>> {
+	let mut findings = Vec::new();
+	for (unused, deps_by_member) in [(true, &outcome.unused_deps), (false, &outcome.declared_used_deps)] {
+		for (id, deps) in deps_by_member {
+			let manifest_path = Path::new(&deps.manifest_path);
+			let manifest_path = manifest_path.strip_prefix(&outcome.workspace_root).unwrap_or(manifest_path);
+			let manifest = outcome.manifests.get(id);
+			for &(kind, kind_name) in KINDS {
+				let level = match (unused, outcome.severity.unused(kind), outcome.severity.declared_used) {
+					(true, Severity::Deny, _) => "error",
+					(true, Severity::Warn, _) => "warning",
+					(false, _, Severity::Deny) => "error",
+					(false, _, Severity::Warn) => "note",
+					_ => continue,
+				};
+				for dep in deps.unused_deps(kind) {
+					let message = if unused {
+						format!("unused {} `{}`", kind_name, dep)
+					} else {
+						format!("{} `{}` is declared as used with `#[cfg(cargo_udeps)]`", kind_name, dep)
+					};
+					findings.push(Finding {
+						level,
+						message,
+						id : *id,
+						manifest_path,
+						manifest,
+						entry : manifest.and_then(|manifest| manifest.dependency(kind, dep)),
+						unused,
+					});
+				}
+			}
+		}
+	}
+	findings
+}
+
+pub(crate) fn print(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	let findings = findings(outcome);
+	for finding in &findings {
+		render(finding, Rendering::default(), &mut stdout)?;
+	}
+
+	if !findings.iter().any(|finding| finding.unused) {
+		writeln!(stdout, "All deps seem to have been used.")?;
+	} else if let Some(note) = &outcome.note {
+		write!(stdout, "{}", note)?;
+	}
+	stdout.flush()
+}
+
+/// Prints one JSON message per finding, like `cargo build --message-format json` does.
+pub(crate) fn print_json(outcome :&Outcome, rendering :Rendering, mut stdout :impl Write) -> io::Result<()> {
+	for finding in findings(outcome) {
+		let mut rendered = Vec::new();
+		render(&finding, rendering, &mut rendered)?;
+		let file_name = finding.manifest_path.to_string_lossy();
+
+		let mut spans = Vec::new();
+		let mut children = Vec::new();
+		if let Some((manifest, entry)) = finding.manifest.zip(finding.entry) {
+			if let Some(span) = &entry.span {
+				spans.push(json_span(manifest, &file_name, span.clone(), true, None));
+			}
+			if finding.unused {
+				let removal = entry.removal.as_ref().map(|removal| {
+					let applicability = if removal.exact { "MachineApplicable" } else { "MaybeIncorrect" };
+					json_span(manifest, &file_name, removal.span.clone(), true, Some(applicability))
+				});
+				children.push(json!({
+					"message": help(entry),
+					"code": null,
+					"level": "help",
+					"spans": Vec::from_iter(removal),
+					"children": [],
+					"rendered": null,
+				}));
+			}
+		}
+		let message = json!({
+			"reason": "compiler-message",
+			"package_id": finding.id.to_spec().to_string(),
+			"manifest_path": outcome.workspace_root.join(finding.manifest_path),
+			"target": outcome.packages.get(&finding.id).and_then(|package| {
+				let targets = package.targets();
+				targets.iter().find(|target| target.is_lib()).or(targets.first()).cloned()
+			}),
+			"message": {
+				"$message_type": "diagnostic",
+				"message": finding.message,
+				"code": null,
+				"level": finding.level,
+				"spans": spans,
+				"children": children,
+				"rendered": String::from_utf8_lossy(&rendered),
+			},
+		});
+		writeln!(stdout, "{}", message)?;
+	}
+	stdout.flush()
+}
+
+/// A span in rustc's JSON diagnostic format.
+fn json_span(
+	manifest :&Manifest,
+	file_name :&str,
+	span :std::ops::Range<usize>,
+	is_primary :bool,
+	applicability :Option<&str>,
+) -> Value {
+	let (line_start, column_start) = manifest.line_column(span.start);
+	let (line_end, column_end) = manifest.line_column(span.end);
+	let text = (line_start..=line_end)
+		.map(|line| {
+			let text = manifest.line(line);
+			let highlight_start = if line == line_start { column_start } else { 1 };
+			let highlight_end = if line == line_end { column_end } else { text.chars().count() + 1 };
+			json!({ "text": text, "highlight_start": highlight_start, "highlight_end": highlight_end })
+		})
+		.collect::<Vec<_>>();
+	json!({
+		"file_name": file_name,
+		"byte_start": span.start,
+		"byte_end": span.end,
+		"line_start": line_start,
+		"line_end": line_end,
+		"column_start": column_start,
+		"column_end": column_end,
+		"is_primary": is_primary,
+		"text": text,
+		"label": null,
+		"suggested_replacement": applicability.map(|_| ""),
+		"suggestion_applicability": applicability,
+		"expansion": null,
+	})
+}
+
+fn render(finding :&Finding<'_>, rendering :Rendering, mut out :impl Write) -> io::Result<()> {
+	let location = finding.manifest
+		.zip(finding.entry)
+		.and_then(|(manifest, entry)| Some((manifest, entry, entry.span.clone()?)));
+	if rendering.short {
+		return match &location {
+			Some((manifest, _, span)) => {
+				let (line, column) = manifest.line_column(span.start);
+				writeln!(out, "{}:{}:{}: {}: {}", finding.manifest_path.display(), line, column, finding.level, finding.message)
+			},
+			None => writeln!(out, "{}: {}: {}", finding.manifest_path.display(), finding.level, finding.message),
+		};
+	}
+
+	// Colored like rustc's diagnostics
+	let level_style = match finding.level {
+		"error" => Color::Red,
+		"warning" => Color::Yellow,
+		_ => Color::Green,
+	}.bold();
+	let paint = |style :Style, text :&str| if rendering.ansi {
+		style.paint(text).to_string()
+	} else {
+		text.to_owned()
+	};
+	let accent = Color::Blue.bold();
+	let bold = Style::new().bold();
+
+	writeln!(out, "{}{}", paint(level_style, finding.level), paint(bold, &format!(": {}", finding.message)))?;
+	let Some((manifest, entry, span)) = location else {
+		writeln!(out, "  {} {}", paint(accent, "-->"), finding.manifest_path.display())?;
+		return writeln!(out);
+	};
+	let (line, column) = manifest.line_column(span.start);
+	let gutter = " ".repeat(line.to_string().len());
+	let text = manifest.line(line);
+	// Underline the rest of the entry's line, starting at the key.
+	let underlined = text.chars().skip(column - 1).collect::<String>();
+	let underline = "^".repeat(underlined.trim_end().chars().count().max(1));
+	let indent = text
+		.chars()
+		.take(column - 1)
+		.map(|c| if c == '\t' { '\t' } else { ' ' })
+		.collect::<String>();
+	let bar = paint(accent, &format!("{} |", gutter));
+
+	writeln!(out, "{}{} {}:{}:{}", gutter, paint(accent, "-->"), finding.manifest_path.display(), line, column)?;
+	writeln!(out, "{}", bar)?;
+	writeln!(out, "{} {}", paint(accent, &format!("{} |", line)), text)?;
+	writeln!(out, "{} {}{}", bar, indent, paint(level_style, &underline))?;
+	writeln!(out, "{}", bar)?;
+	let equals = paint(accent, &format!("{} =", gutter));
+	if entry.inherited {
+		writeln!(
+			out,
+			"{} {}: `{}` is inherited from `[workspace.dependencies]`, which can be removed too if no other member uses it",
+			equals,
+			paint(bold, "note"),
+			entry.name_in_toml,
+		)?;
+	}
+	if finding.unused {
+		writeln!(out, "{} {}: {}", equals, paint(bold, "help"), help(entry))?;
+	}
+	writeln!(out)
+}
+
+/// The suggested action for an unused dependency.
+pub(crate) fn help(entry :&ManifestDependency) -> String {
+	if entry.is_table {
+		format!("remove the `{}` table", entry.name_in_toml)
+	} else {
+		"remove this line".to_owned()
+	}
+}
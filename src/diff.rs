@@ -0,0 +1,206 @@
+//! `cargo udeps diff`: comparing two saved JSON reports.
+//!
+//! Both `--output json` and `--output json-v2` reports can be read.
+//! Members are matched by package name, so reports of different versions
+//! and of checkouts in different directories can be compared.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use cargo::CargoResult;
+use clap::Parser;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::Outcome;
+
+const KINDS :[&str; 3] = ["normal", "development", "build"];
+
+#[derive(Parser, Debug)]
+pub(crate) struct OptDiff {
+	#[arg(value_name("OLD"), help("The older JSON report"))]
+	old :PathBuf,
+	#[arg(value_name("NEW"), help("The newer JSON report"))]
+	new :PathBuf,
+	#[arg(
+		long,
+		value_name("OUTPUT"),
+		default_value("human"),
+		value_parser(["human", "json"]),
+		help("Output format")
+	)]
+	output :String,
+}
+
+/// The kinds each unused dependency has, by member name.
+pub(crate) type UnusedDeps = BTreeMap<String, BTreeMap<String, BTreeSet<String>>>;
+
+#[derive(Debug, Serialize)]
+struct DiffReport {
+	members :BTreeMap<String, MemberDiff>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct MemberDiff {
+	appeared :Vec<DepKinds>,
+	disappeared :Vec<DepKinds>,
+	changed_kind :Vec<ChangedKind>,
+}
+
+#[derive(Debug, Serialize)]
+struct DepKinds {
+	name :String,
+	kinds :BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChangedKind {
+	name :String,
+	old_kinds :BTreeSet<String>,
+	new_kinds :BTreeSet<String>,
+}
+
+impl OptDiff {
+	pub(crate) fn run(&self, cwd :&Path, mut stdout :impl Write) -> CargoResult<()> {
+		let old = load(&cwd.join(&self.old))?;
+		let new = load(&cwd.join(&self.new))?;
+		let diff = diff(&old, &new);
+		if self.output == "json" {
+			let json = serde_json::to_string(&DiffReport { members : diff }).expect("should not fail");
+			writeln!(stdout, "{}", json)?;
+		} else {
+			print_human(&diff, &mut stdout)?;
+		}
+		stdout.flush()?;
+		Ok(())
+	}
+}
+
+/// The unused dependencies of an analysis.
+pub(crate) fn unused_deps_of(outcome :&Outcome) -> UnusedDeps {
+	let mut deps = UnusedDeps::new();
+	for (id, member_deps) in &outcome.unused_deps {
+		for (kind, names) in KINDS.iter().zip([&member_deps.normal, &member_deps.development, &member_deps.build]) {
+			for name in names {
+				deps.entry(id.name().to_string())
+					.or_default()
+					.entry(name.to_string())
+					.or_default()
+					.insert((*kind).to_owned());
+			}
+		}
+	}
+	deps
+}
+
+/// Prints what changed between two sets of unused dependencies.
+pub(crate) fn print_changes(old :&UnusedDeps, new :&UnusedDeps, stdout :impl Write) -> io::Result<()> {
+	print_human(&diff(old, new), stdout)
+}
+
+/// Reads the unused dependencies of a report.
+fn load(path :&Path) -> CargoResult<UnusedDeps> {
+	let json = fs::read_to_string(path)
+		.with_context(|| format!("could not read `{}`", path.display()))?;
+	let report = serde_json::from_str::<Value>(&json)
+		.with_context(|| format!("could not parse `{}`", path.display()))?;
+	unused_deps(&report)
+		.with_context(|| format!("`{}` is not a JSON report of cargo-udeps", path.display()))
+}
+
+fn unused_deps(report :&Value) -> Option<UnusedDeps> {
+	let mut deps = UnusedDeps::new();
+	match report.get("version") {
+		// `--output json-v2`
+		Some(version) => {
+			if version.as_u64()? != 2 {
+				return None;
+			}
+			for finding in report.get("findings")?.as_array()? {
+				if finding.get("category")?.as_str()? != "unused" {
+					continue;
+				}
+				let member = finding.get("package")?.get("name")?.as_str()?;
+				let dependency = finding.get("dependency")?;
+				deps.entry(member.to_owned())
+					.or_default()
+					.entry(dependency.get("name_in_toml")?.as_str()?.to_owned())
+					.or_default()
+					.insert(dependency.get("kind")?.as_str()?.to_owned());
+			}
+		},
+		// `--output json`, where members are keyed by their package ID
+		None => {
+			for (id, member_deps) in report.get("unused_deps")?.as_object()? {
+				let member = id.split_whitespace().next()?;
+				for kind in KINDS {
+					for dep in member_deps.get(kind)?.as_array()? {
+						deps.entry(member.to_owned())
+							.or_default()
+							.entry(dep.as_str()?.to_owned())
+							.or_default()
+							.insert(kind.to_owned());
+					}
+				}
+			}
+		},
+	}
+	Some(deps)
+}
+
+fn diff(old :&UnusedDeps, new :&UnusedDeps) -> BTreeMap<String, MemberDiff> {
+	let empty = BTreeMap::new();
+	let mut diffs = BTreeMap::new();
+	for member in old.keys().chain(new.keys()).collect::<BTreeSet<_>>() {
+		let old = old.get(member).unwrap_or(&empty);
+		let new = new.get(member).unwrap_or(&empty);
+		let mut diff = MemberDiff::default();
+		for name in old.keys().chain(new.keys()).collect::<BTreeSet<_>>() {
+			match (old.get(name), new.get(name)) {
+				(None, Some(kinds)) => diff.appeared.push(DepKinds { name : name.clone(), kinds : kinds.clone() }),
+				(Some(kinds), None) => diff.disappeared.push(DepKinds { name : name.clone(), kinds : kinds.clone() }),
+				(Some(old_kinds), Some(new_kinds)) if old_kinds != new_kinds => diff.changed_kind.push(ChangedKind {
+					name : name.clone(),
+					old_kinds : old_kinds.clone(),
+					new_kinds : new_kinds.clone(),
+				}),
+				_ => {},
+			}
+		}
+		if !diff.appeared.is_empty() || !diff.disappeared.is_empty() || !diff.changed_kind.is_empty() {
+			diffs.insert(member.clone(), diff);
+		}
+	}
+	diffs
+}
+
+fn print_human(diffs :&BTreeMap<String, MemberDiff>, mut stdout :impl Write) -> io::Result<()> {
+	if diffs.is_empty() {
+		return writeln!(stdout, "No changes in unused dependencies.");
+	}
+	let kinds = |kinds :&BTreeSet<String>| kinds.iter().map(String::as_str).collect::<Vec<_>>().join(", ");
+	for (member, diff) in diffs {
+		writeln!(stdout, "`{}`", member)?;
+		let sections = [
+			("appeared", diff.appeared.iter().map(|dep| format!("{:?} ({})", dep.name, kinds(&dep.kinds))).collect::<Vec<_>>()),
+			("disappeared", diff.disappeared.iter().map(|dep| format!("{:?} ({})", dep.name, kinds(&dep.kinds))).collect()),
+			("changed kind", diff.changed_kind
+				.iter()
+				.map(|dep| format!("{:?} ({} → {})", dep.name, kinds(&dep.old_kinds), kinds(&dep.new_kinds)))
+				.collect()),
+		];
+		let sections = sections.iter().filter(|(_, lines)| !lines.is_empty()).collect::<Vec<_>>();
+		for (i, (header, lines)) in sections.iter().enumerate() {
+			let (edge, joint) = if i + 1 == sections.len() { (' ', '└') } else { ('│', '├') };
+			writeln!(stdout, "{}─── {}", joint, header)?;
+			for (j, line) in lines.iter().enumerate() {
+				let joint = if j + 1 == lines.len() { '└' } else { '├' };
+				writeln!(stdout, "{}    {}─── {}", edge, joint, line)?;
+			}
+		}
+	}
+	Ok(())
+}
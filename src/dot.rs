@@ -0,0 +1,95 @@
+//! `--output dot`: the direct dependencies of the workspace members as a Graphviz graph.
+//!
+//! Edges are colored by kind and styled by verdict. Packages that are only reachable
+//! through unused dependencies are filled, and so are the edges between them.
+//! Packages are identified by name, so several versions of one package share a node.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+
+use cargo::core::dependency::DepKind;
+
+use crate::{kind_name, DependencyStatus, Outcome};
+
+const HIGHLIGHT :&str = "#f4a3a3";
+
+/// The edge style of a status.
+fn style(status :DependencyStatus) -> &'static str {
+	match status {
+		DependencyStatus::Used | DependencyStatus::DeclaredUsed => "solid",
+		DependencyStatus::Unused | DependencyStatus::Baselined | DependencyStatus::Allowed => "bold",
+		DependencyStatus::Ignored => "dashed",
+		DependencyStatus::NotEvaluated => "dotted",
+	}
+}
+
+fn color(kind :DepKind) -> &'static str {
+	match kind {
+		DepKind::Normal => "black",
+		DepKind::Development => "blue",
+		DepKind::Build => "darkgreen",
+	}
+}
+
+pub(crate) fn print(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	let member_names = outcome.packages.keys().map(|id| id.name().to_string()).collect::<BTreeSet<_>>();
+
+	// (member, package, kind, name_in_toml) -> status
+	let mut edges = BTreeMap::new();
+	let mut highlighted = BTreeSet::new();
+	let mut highlighted_edges = BTreeSet::new();
+	for ((id, kind, name_in_toml), dep) in outcome.declared_dependencies() {
+		if matches!(dep.status, DependencyStatus::Unused | DependencyStatus::Baselined)
+			&& let Some(savings) = outcome.savings.get(&(id, kind, name_in_toml))
+		{
+			highlighted.extend(savings.packages.iter().map(|pkg| pkg.name().to_string()));
+			highlighted_edges.extend(
+				savings.edges.iter().map(|(from, to)| (from.name().to_string(), to.name().to_string())),
+			);
+		}
+		edges.insert((id.name().to_string(), dep.package_name.to_string(), kind, name_in_toml.to_string()), dep.status);
+	}
+	let packages = edges
+		.keys()
+		.map(|(_, package, _, _)| package.clone())
+		.chain(highlighted_edges.iter().flat_map(|(from, to)| [from.clone(), to.clone()]))
+		.filter(|package| !member_names.contains(package))
+		.collect::<BTreeSet<_>>();
+
+	writeln!(stdout, "digraph udeps {{")?;
+	writeln!(stdout, "\trankdir=LR;")?;
+	writeln!(stdout, "\tnode [fontname=\"sans-serif\"];")?;
+	writeln!(stdout, "\tedge [fontname=\"sans-serif\"];")?;
+	writeln!(stdout)?;
+	for member in &member_names {
+		writeln!(stdout, "\t{} [shape=box, style=bold];", quote(member))?;
+	}
+	for package in &packages {
+		if highlighted.contains(package) {
+			writeln!(stdout, "\t{} [style=filled, fillcolor={}];", quote(package), quote(HIGHLIGHT))?;
+		} else {
+			writeln!(stdout, "\t{};", quote(package))?;
+		}
+	}
+	writeln!(stdout)?;
+	for ((member, package, kind, name_in_toml), status) in &edges {
+		let mut attributes = vec![
+			format!("color={}", color(*kind)),
+			format!("style={}", style(*status)),
+			format!("tooltip={}", quote(&format!("{} {}", kind_name(*kind), status.name()))),
+		];
+		if name_in_toml != package {
+			attributes.push(format!("label={}", quote(name_in_toml)));
+		}
+		writeln!(stdout, "\t{} -> {} [{}];", quote(member), quote(package), attributes.join(", "))?;
+	}
+	for (from, to) in &highlighted_edges {
+		writeln!(stdout, "\t{} -> {} [color={}];", quote(from), quote(to), quote(HIGHLIGHT))?;
+	}
+	writeln!(stdout, "}}")?;
+	stdout.flush()
+}
+
+fn quote(id :&str) -> String {
+	format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -0,0 +1,165 @@
+//! `--output html`: a single static HTML file with a sortable table of all direct dependencies.
+//!
+//! Styles and the sorting script are inline, so the file can be archived and opened offline.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::{kind_name, DependencyStatus, Outcome, Severity};
+
+const STYLE :&str = "\
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th[data-order=asc]::after { content: \" \\25b2\"; }
+th[data-order=desc]::after { content: \" \\25bc\"; }
+td.number { text-align: right; }
+tr.unused { background: #fde0e0; }
+tr.warning, tr.baselined { background: #fdf3d8; }
+tr.allowed, tr.ignored, tr.not-evaluated { color: #777; }
+.meta { color: #777; }
+";
+
+const SCRIPT :&str = "\
+document.querySelectorAll(\"th\").forEach((th, column) => th.addEventListener(\"click\", () => {
+	const tbody = th.closest(\"table\").tBodies[0];
+	const ascending = th.dataset.order !== \"asc\";
+	th.parentElement.querySelectorAll(\"th\").forEach(other => delete other.dataset.order);
+	th.dataset.order = ascending ? \"asc\" : \"desc\";
+	const key = row => row.cells[column].dataset.value ?? row.cells[column].textContent;
+	const compare = th.dataset.type === \"number\"
+		? (a, b) => Number(key(a)) - Number(key(b))
+		: (a, b) => key(a).localeCompare(key(b));
+	tbody.append(...Array.from(tbody.rows).sort((a, b) => ascending ? compare(a, b) : compare(b, a)));
+}));
+";
+
+pub(crate) fn print(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	let declared = outcome.declared_dependencies();
+	let mut counts = BTreeMap::<_, usize>::new();
+	for dep in declared.values() {
+		*counts.entry(dep.status.name()).or_default() += 1;
+	}
+
+	let mut html = String::new();
+	html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+	html.push_str("<title>cargo-udeps report</title>\n");
+	writeln!(html, "<style>\n{}</style>", STYLE).unwrap();
+	html.push_str("</head>\n<body>\n<h1>cargo-udeps report</h1>\n");
+	write!(html, "<p class=\"meta\">cargo-udeps {}", env!("CARGO_PKG_VERSION")).unwrap();
+	if let Some(toolchain) = outcome.toolchain.as_deref().and_then(|toolchain| toolchain.lines().next()) {
+		write!(html, ", {}", escape(toolchain)).unwrap();
+	}
+	html.push_str("</p>\n");
+	let summary = counts
+		.iter()
+		.map(|(status, count)| format!("{} {}", count, status))
+		.collect::<Vec<_>>();
+	writeln!(
+		html,
+		"<p>{} direct dependencies of {} members: {}.</p>",
+		declared.len(),
+		outcome.packages.len(),
+		summary.join(", "),
+	).unwrap();
+
+	html.push_str("<table>\n<thead>\n<tr>");
+	for (header, kind) in [
+		("Member", "text"),
+		("Dependency", "text"),
+		("Kind", "text"),
+		("Verdict", "text"),
+		("Ignore reason", "text"),
+		("Used by", "text"),
+		("Removed packages", "number"),
+		("Compile time (s)", "number"),
+	] {
+		write!(html, "<th data-type=\"{}\">{}</th>", kind, header).unwrap();
+	}
+	html.push_str("</tr>\n</thead>\n<tbody>\n");
+	for ((id, kind, name_in_toml), dep) in &declared {
+		let key = (*id, *kind, *name_in_toml);
+		let (class, verdict) = match dep.status {
+			DependencyStatus::Unused if outcome.severity.unused(*kind) == Severity::Warn => ("warning", "unused (warning)".to_owned()),
+			status => (css_class(status), status.name().to_owned()),
+		};
+		let reason = outcome.ignored_deps
+			.get(id)
+			.and_then(|deps| deps.ignored_deps(*kind).get(name_in_toml))
+			.and_then(Option::as_deref)
+			.unwrap_or("");
+		let used_by = outcome.used_in
+			.get(&key)
+			.map(|targets| targets.iter().map(|target| escape(target)).collect::<Vec<_>>().join("<br>"))
+			.unwrap_or_default();
+		let savings = outcome.savings.get(&key);
+		let name = if **name_in_toml == *dep.package_name {
+			escape(name_in_toml)
+		} else {
+			format!("{} ({})", escape(name_in_toml), escape(&dep.package_name))
+		};
+
+		write!(html, "<tr class=\"{}\">", class).unwrap();
+		write!(html, "<td>{}</td>", escape(&id.name())).unwrap();
+		write!(html, "<td>{}</td>", name).unwrap();
+		write!(html, "<td>{}</td>", kind_name(*kind)).unwrap();
+		write!(html, "<td>{}</td>", verdict).unwrap();
+		write!(html, "<td>{}</td>", escape(reason)).unwrap();
+		write!(html, "<td>{}</td>", used_by).unwrap();
+		match savings {
+			Some(savings) => {
+				let packages = savings.packages
+					.iter()
+					.map(|pkg| format!("{}@{}", pkg.name(), pkg.version()))
+					.collect::<Vec<_>>();
+				write!(
+					html,
+					"<td class=\"number\" data-value=\"{0}\" title=\"{1}\">{0}</td>",
+					packages.len(),
+					escape(&packages.join(", ")),
+				).unwrap();
+			},
+			None => html.push_str("<td class=\"number\" data-value=\"0\"></td>"),
+		}
+		match savings.and_then(|savings| savings.compile_time) {
+			Some(time) => write!(html, "<td class=\"number\" data-value=\"{0}\">{0:.2}</td>", time.as_secs_f64()).unwrap(),
+			None => html.push_str("<td class=\"number\" data-value=\"0\"></td>"),
+		}
+		html.push_str("</tr>\n");
+	}
+	html.push_str("</tbody>\n</table>\n");
+
+	if !outcome.notes.is_empty() {
+		html.push_str("<h2>Notes</h2>\n<ul>\n");
+		for note in &outcome.notes {
+			writeln!(html, "<li>{}</li>", escape(note.message())).unwrap();
+		}
+		html.push_str("</ul>\n");
+	}
+	writeln!(html, "<script>\n{}</script>", SCRIPT).unwrap();
+	html.push_str("</body>\n</html>\n");
+
+	stdout.write_all(html.as_bytes())?;
+	stdout.flush()
+}
+
+fn css_class(status :DependencyStatus) -> &'static str {
+	match status {
+		DependencyStatus::Used => "used",
+		DependencyStatus::DeclaredUsed => "declared-used",
+		DependencyStatus::Unused => "unused",
+		DependencyStatus::Baselined => "baselined",
+		DependencyStatus::Allowed => "allowed",
+		DependencyStatus::Ignored => "ignored",
+		DependencyStatus::NotEvaluated => "not-evaluated",
+	}
+}
+
+fn escape(text :&str) -> String {
+	text.replace('&', "&amp;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+		.replace('"', "&quot;")
+}
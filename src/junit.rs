@@ -0,0 +1,127 @@
+//! `--output junit`: JUnit XML, for CI systems that render test reports.
+//!
+//! Each workspace member is a test suite, and each of its dependencies a test case.
+//! Unused dependencies fail, unless they are only warned about, and ignored, baselined
+//! or not evaluated dependencies are skipped.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use cargo::core::dependency::DepKind;
+use cargo::core::package_id::PackageId;
+use cargo::util::interning::InternedString;
+
+use crate::{DependencyStatus, Outcome, Severity};
+
+enum Case<'a> {
+	Passed { output :Option<&'static str> },
+	Failed,
+	Skipped { reason :Option<&'a str> },
+}
+
+pub(crate) fn print(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	let mut suites = BTreeMap::<PackageId, BTreeMap<(DepKind, InternedString), Case<'_>>>::new();
+	for ((id, kind, dep), declared) in outcome.declared_dependencies() {
+		let case = match declared.status {
+			DependencyStatus::Used => Case::Passed { output : None },
+			DependencyStatus::DeclaredUsed => Case::Passed { output : Some("declared as used with `#[cfg(cargo_udeps)]`") },
+			DependencyStatus::Unused if outcome.severity.unused(kind) == Severity::Deny => Case::Failed,
+			DependencyStatus::Unused => Case::Passed { output : Some("unused, reported as a warning") },
+			DependencyStatus::Allowed => Case::Passed { output : Some("unused, not reported with the `allow` severity") },
+			DependencyStatus::Baselined => Case::Skipped { reason : Some("unused, accepted by the baseline") },
+			DependencyStatus::Ignored => {
+				let reason = outcome.ignored_deps
+					.get(&id)
+					.and_then(|deps| deps.ignored_deps(kind).get(&dep))
+					.and_then(Option::as_deref);
+				Case::Skipped { reason }
+			},
+			DependencyStatus::NotEvaluated => Case::Skipped { reason : Some("not evaluated, for example because it is for another platform") },
+		};
+		suites.entry(id).or_default().insert((kind, dep), case);
+	}
+
+	let note = outcome.note.as_deref().unwrap_or("");
+	let count = |cases :&BTreeMap<_, Case<'_>>, f :fn(&Case<'_>) -> bool| cases.values().filter(|case| f(case)).count();
+	let is_failed = |case :&Case<'_>| matches!(case, Case::Failed);
+	let is_skipped = |case :&Case<'_>| matches!(case, Case::Skipped { .. });
+
+	let mut xml = String::new();
+	let all_cases = suites.values().flat_map(|cases| cases.values());
+	writeln!(xml, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+	writeln!(
+		xml,
+		r#"<testsuites name="cargo-udeps" tests="{}" failures="{}" skipped="{}">"#,
+		all_cases.clone().count(),
+		all_cases.clone().filter(|case| is_failed(case)).count(),
+		all_cases.filter(|case| is_skipped(case)).count(),
+	).unwrap();
+	for (id, cases) in &suites {
+		writeln!(
+			xml,
+			r#"  <testsuite name="{}" tests="{}" failures="{}" errors="0" skipped="{}">"#,
+			escape(&id.to_string()),
+			cases.len(),
+			count(cases, is_failed),
+			count(cases, is_skipped),
+		).unwrap();
+		for ((kind, dep), case) in cases {
+			let classname = format!("{}.{}", id.name(), table_name(*kind));
+			write!(xml, r#"    <testcase name="{}" classname="{}""#, escape(dep), escape(&classname)).unwrap();
+			match case {
+				Case::Passed { output : None } => writeln!(xml, "/>").unwrap(),
+				Case::Passed { output : Some(output) } => {
+					writeln!(xml, ">").unwrap();
+					writeln!(xml, "      <system-out>{}</system-out>", escape(output)).unwrap();
+					writeln!(xml, "    </testcase>").unwrap();
+				},
+				Case::Failed => {
+					writeln!(xml, ">").unwrap();
+					writeln!(
+						xml,
+						r#"      <failure message="unused dependency `{}`" type="unused-dependency">{}</failure>"#,
+						escape(dep),
+						escape(note),
+					).unwrap();
+					writeln!(xml, "    </testcase>").unwrap();
+				},
+				Case::Skipped { reason } => {
+					writeln!(xml, ">").unwrap();
+					match reason {
+						Some(reason) => writeln!(xml, r#"      <skipped message="{}"/>"#, escape(reason)).unwrap(),
+						None => writeln!(xml, "      <skipped/>").unwrap(),
+					}
+					writeln!(xml, "    </testcase>").unwrap();
+				},
+			}
+		}
+		writeln!(xml, "  </testsuite>").unwrap();
+	}
+	writeln!(xml, "</testsuites>").unwrap();
+	stdout.write_all(xml.as_bytes())?;
+	stdout.flush()
+}
+
+fn table_name(kind :DepKind) -> &'static str {
+	match kind {
+		DepKind::Normal => "dependencies",
+		DepKind::Development => "dev-dependencies",
+		DepKind::Build => "build-dependencies",
+	}
+}
+
+fn escape(s :&str) -> String {
+	let mut escaped = String::with_capacity(s.len());
+	for c in s.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&apos;"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
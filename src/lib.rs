@@ -1,4 +1,5 @@
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap, HashSet};
 use std::ffi::OsString;
 use std::fmt::Write as _;
 use std::io::{self, Write};
@@ -7,35 +8,124 @@ use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use std::{env, fmt};
 
 use nu_ansi_term::Color;
-use cargo::core::compiler::{CompileMode, UserIntent, DefaultExecutor, Executor, RustcTargetData, Unit};
+use cargo::core::compiler::{BuildConfig, CompileKind, CompileMode, MessageFormat, UserIntent, DefaultExecutor, Executor, RustcTargetData, Unit};
 use cargo::core::resolver::HasDevUnits;
 use cargo::core::resolver::features::{ForceAllTargets, CliFeatures};
-use cargo::core::manifest::Target;
+use cargo::core::manifest::{Target, TargetKind};
 use cargo::core::package_id::PackageId;
 use cargo::core::shell::Shell;
 use cargo::core::{dependency, Package, Resolve, Workspace, Verbosity};
-use cargo::ops::Packages;
-use cargo::util::command_prelude::{ArgMatchesExt, ProfileChecking};
-use cargo::util::context::GlobalContext;
+use cargo::ops::{CompileFilter, CompileOptions, Packages};
+use cargo::util::command_prelude;
+use cargo::util::context::{GlobalContext, JobsConfig};
 use cargo::util::interning::InternedString;
 use cargo_util::ProcessBuilder;
+use cargo_platform::{Cfg, CfgExpr};
 use cargo::{CargoResult, CliError, CliResult};
 use serde::{Deserialize, Serialize};
 use clap::{ArgAction, ArgMatches, CommandFactory, Parser};
-
-pub fn run<I: IntoIterator<Item = OsString>, W: Write>(args :I, config :&mut GlobalContext, stdout: W) -> CliResult {
-	let args = args.into_iter().collect::<Vec<_>>();
-	let Opt::Udeps(opt) = Opt::try_parse_from(&args)?;
-	let clap_matches = Opt::command().try_get_matches_from(args)?;
-	match opt.run(config, stdout, clap_matches.subcommand_matches("udeps").unwrap())? {
+use clap::parser::ValueSource;
+
+use crate::backend::{Artifacts, UsedCrate};
+use crate::baseline::Baseline;
+use crate::config::{Config, ConfigOptions, SeverityPolicy};
+use crate::diagnostic::Rendering;
+use crate::manifest::Manifest;
+use crate::savings::Savings;
+
+mod api;
+mod backend;
+mod baseline;
+mod config;
+mod diagnostic;
+mod diff;
+mod dot;
+mod html;
+mod junit;
+mod lsp;
+mod manifest;
+mod markdown;
+mod report;
+mod sarif;
+mod savings;
+mod watch;
+
+pub use crate::api::{DependencyKind, DependencyReport, DependencyVerdict, Report, Udeps};
+
+/// Exit code if there are unused dependencies with the `deny` severity.
+pub const EXIT_UNUSED :i32 = 1;
+/// Exit code if the command line or the configuration is invalid.
+pub const EXIT_CONFIG_ERROR :i32 = 2;
+/// Exit code if the build failed, or any other error occurred.
+pub const EXIT_BUILD_FAILED :i32 = 101;
+
+pub fn run<I: IntoIterator<Item = OsString>, W: Write>(args :I, config :&mut GlobalContext, mut stdout: W) -> CliResult {
+	let (opt, clap_matches) = parse_args(args)?;
+	if let Some(UdepsCommand::Diff(diff)) = &opt.command {
+		reject_analysis_args(&clap_matches, "diff")?;
+		return diff.run(config.cwd(), stdout).map_err(config_error);
+	}
+	let options = opt.analysis_options(&clap_matches);
+	if let Some(UdepsCommand::Lsp) = &opt.command {
+		return Ok(lsp::serve(&options, config, io::stdin().lock(), stdout)?);
+	}
+	if opt.watch {
+		return watch::run(&options, config, stdout);
+	}
+	let outcome = options.outcome(config, Some(&mut stdout))?;
+	match options.print(&outcome, stdout)? {
 		0 => Ok(()),
 		code => Err(CliError::code(code)),
 	}
 }
 
+/// Parses the arguments of `cargo udeps`, returning the matches of the `udeps` subcommand.
+fn parse_args<I: IntoIterator<Item = OsString>>(args :I) -> Result<(OptUdeps, ArgMatches), CliError> {
+	let args = args.into_iter().collect::<Vec<_>>();
+	let Opt::Udeps(opt) = Opt::try_parse_from(&args).map_err(clap_error)?;
+	let (_, clap_matches) = Opt::command()
+		.try_get_matches_from(args)
+		.map_err(clap_error)?
+		.remove_subcommand()
+		.expect("`udeps` is the only subcommand");
+	Ok((opt, clap_matches))
+}
+
+/// Fails if an analysis option was given to `subcommand`, which doesn't analyze anything.
+fn reject_analysis_args(clap_matches :&ArgMatches, subcommand :&str) -> Result<(), CliError> {
+	let mut command = Opt::command();
+	// Sets the usage to `cargo udeps`
+	command.build();
+	let udeps = command.find_subcommand_mut("udeps").expect("`udeps` is the only subcommand");
+	let given = udeps
+		.get_arguments()
+		.find(|arg| clap_matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+		.map(|arg| match arg.get_long() {
+			Some(long) => format!("--{}", long),
+			None => arg.get_id().to_string(),
+		});
+	match given {
+		Some(name) => Err(clap_error(udeps.error(
+			clap::error::ErrorKind::ArgumentConflict,
+			format!("the argument '{}' cannot be used with the `{}` subcommand", name, subcommand),
+		))),
+		None => Ok(()),
+	}
+}
+
+fn clap_error(err :clap::Error) -> CliError {
+	let code = if err.use_stderr() { EXIT_CONFIG_ERROR } else { 0 };
+	CliError::new(err.into(), code)
+}
+
+fn config_error(err :anyhow::Error) -> CliError {
+	CliError::new(err, EXIT_CONFIG_ERROR)
+}
+
 #[derive(Parser, Debug)]
 #[command(
 	about,
@@ -180,7 +270,7 @@ struct OptUdeps {
 		value_name("FMT"),
 		id = "message-format",
 		ignore_case(true),
-		value_parser(["human", "json", "short"]),
+		value_parser(["human", "json", "short", "json-diagnostic-short", "json-diagnostic-rendered-ansi"]),
 		default_value("human"),
 		help("[cargo] Error format")
 	)]
@@ -214,6 +304,47 @@ struct OptUdeps {
 		help("Output format"))
 	]
 	output: OutputKind,
+	#[arg(
+		long,
+		value_name("NAME"),
+		num_args(1..),
+		number_of_values(1),
+		help("Run the named configuration (`all` for every one) and merge the results")
+	)]
+	config_set :Vec<String>,
+	#[arg(
+		long,
+		value_name("CATEGORY=LEVEL"),
+		num_args(1..),
+		number_of_values(1),
+		value_parser = parse_severity,
+		help("Set how findings are reported: `allow`, `warn` or `deny` for `unused`, `normal`, `development`, `build` or `declared-used`")
+	)]
+	severity :Vec<(String, Severity)>,
+	#[arg(
+		long,
+		value_name("PATH"),
+		help("Only fail on findings that are not in this baseline, and report baseline entries that have been fixed")
+	)]
+	baseline :Option<PathBuf>,
+	#[arg(
+		long,
+		value_name("PATH"),
+		help("Write the current findings to a baseline file")
+	)]
+	write_baseline :Option<PathBuf>,
+	#[arg(
+		long,
+		help("Show what removing each unused dependency would save, including how long its packages took to compile in this run"),
+		value_parser = clap::value_parser!(bool),
+	)]
+	compile_times :bool,
+	#[arg(
+		long,
+		help("Analyze again whenever a source file or manifest of the workspace changes"),
+		value_parser = clap::value_parser!(bool),
+	)]
+	watch :bool,
 	#[arg(
 		long,
 		value_name("BACKEND"),
@@ -222,6 +353,12 @@ struct OptUdeps {
 		help("Backend to use for determining unused deps"))
 	]
 	backend :Backend,
+	#[arg(
+		long,
+		value_name("PATH"),
+		help("Program for the `external` backend"),
+	)]
+	backend_command :Option<PathBuf>,
 	#[arg(
 		long,
 		id = "keep-going",
@@ -229,15 +366,128 @@ struct OptUdeps {
 		value_parser = clap::value_parser!(bool),
 	)]
 	keep_going :bool,
+	#[command(subcommand)]
+	command :Option<UdepsCommand>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum UdepsCommand {
+	/// Compare the unused dependencies of two saved JSON reports, without compiling anything
+	Diff(diff::OptDiff),
+	/// Run a language server on stdin and stdout that shows unused dependencies in `Cargo.toml`
+	Lsp,
+}
+
+/// The options of an analysis, from the command line or from [`Udeps`].
+///
+/// The options that the configuration can set too are `None` if they weren't given.
+#[derive(Clone, Debug, Default)]
+struct AnalysisOptions {
+	quiet :bool,
+	verbose :u8,
+	color :Option<String>,
+	frozen :bool,
+	locked :bool,
+	offline :bool,
+	target_dir :Option<PathBuf>,
+	manifest_path :Option<PathBuf>,
+	packages :Vec<String>,
+	workspace :bool,
+	exclude :Vec<String>,
+	jobs :Option<String>,
+	keep_going :bool,
+	lib :bool,
+	bin :Vec<String>,
+	bins :bool,
+	example :Vec<String>,
+	examples :bool,
+	test :Vec<String>,
+	tests :bool,
+	bench :Vec<String>,
+	benches :bool,
+	all_targets :bool,
+	release :bool,
+	profile :Option<String>,
+	features :Option<Vec<String>>,
+	all_features :bool,
+	no_default_features :bool,
+	target :Option<String>,
+	message_format :Vec<String>,
+	output :Option<OutputKind>,
+	config_set :Vec<String>,
+	severity :Vec<(String, Severity)>,
+	baseline :Option<PathBuf>,
+	write_baseline :Option<PathBuf>,
+	compile_times :bool,
+	backend :Option<Backend>,
+	backend_command :Option<PathBuf>,
 }
 
 impl OptUdeps {
-	fn run<W: Write>(
+	fn analysis_options(&self, clap_matches :&ArgMatches) -> AnalysisOptions {
+		let from_command_line = |id :&str| clap_matches.value_source(id) == Some(ValueSource::CommandLine);
+		AnalysisOptions {
+			quiet : self.quiet,
+			verbose : self.verbose,
+			color : self.color.clone(),
+			frozen : self.frozen,
+			locked : self.locked,
+			offline : self.offline,
+			target_dir : self.target_dir.clone(),
+			manifest_path : self.manifest_path.as_ref().map(PathBuf::from),
+			packages : self.package.clone(),
+			workspace : self.workspace || self.all,
+			exclude : self.exclude.clone(),
+			jobs : self.jobs.clone(),
+			keep_going : self.keep_going,
+			lib : self.lib,
+			bin : self.bin.clone(),
+			bins : self.bins,
+			example : self.example.clone(),
+			examples : self.examples,
+			test : self.test.clone(),
+			tests : self.tests,
+			bench : self.bench.clone(),
+			benches : self.benches,
+			all_targets : self.all_targets,
+			release : self.release,
+			profile : self.profile.clone(),
+			features : from_command_line("features").then(|| self.features.clone()),
+			all_features : self.all_features,
+			no_default_features : self.no_default_features,
+			target : self.target.clone(),
+			message_format : self.message_format.clone(),
+			output : from_command_line("output").then_some(self.output),
+			config_set : self.config_set.clone(),
+			severity : self.severity.clone(),
+			baseline : self.baseline.clone(),
+			write_baseline : self.write_baseline.clone(),
+			compile_times : self.compile_times,
+			backend : from_command_line("backend").then_some(self.backend),
+			backend_command : self.backend_command.clone(),
+		}
+	}
+}
+
+impl AnalysisOptions {
+	/// Analyzes the workspace.
+	///
+	/// With `--message-format json`, the messages are written to `messages` member by member once the build is done.
+	fn outcome(
+		&self,
+		config :&mut GlobalContext,
+		messages :Option<&mut dyn Write>,
+	) -> Result<Outcome, CliError> {
+		self.outcome_incremental(config, &mut Incremental::default(), messages)
+	}
+
+	/// Like `outcome`, but only analyzes the members in `incremental.changed` again.
+	fn outcome_incremental(
 		&self,
 		config :&mut GlobalContext,
-		stdout :W,
-		clap_matches :&ArgMatches
-	) -> CargoResult<i32> {
+		incremental :&mut Incremental,
+		messages :Option<&mut dyn Write>,
+	) -> Result<Outcome, CliError> {
 		if self.verbose > 0 {
 			let mut shell = config.shell();
 			shell.warn(
@@ -259,33 +509,409 @@ impl OptUdeps {
 			&[],
 		)?;
 		assert!(config.nightly_features_allowed);
-		let ws = clap_matches.workspace(config)?;
-		let test = match self.profile.as_deref() {
-			None => false,
+		let root_manifest = command_prelude::root_manifest(self.manifest_path.as_deref(), config).map_err(config_error)?;
+		let ws = Workspace::new(&root_manifest, config).map_err(config_error)?;
+
+		use anyhow::Context;
+		let WorkspaceMetadataCargoUdeps {
+			options: metadata_options,
+			configs: metadata_configs,
+			severity: metadata_severity,
+			ignore: workspace_ignore,
+			unknown,
+		} = ws
+			.custom_metadata()
+			.map::<CargoResult<_>, _>(|workspace_metadata| {
+				let WorkspaceMetadata { cargo_udeps } = workspace_metadata
+					.clone()
+					.try_into()
+					.with_context(|| format!(
+						"could not parse `workspace.metadata.cargo-udeps` in `{}`",
+						ws.root_manifest().display(),
+					))?;
+				Ok(cargo_udeps)
+			})
+			.transpose()
+			.map_err(config_error)?
+			.unwrap_or_default();
+		if let Some(key) = unknown.keys().next() {
+			return Err(config_error(anyhow::anyhow!(
+				"unknown key `{}` in `workspace.metadata.cargo-udeps` in `{}`",
+				key,
+				ws.root_manifest().display(),
+			)));
+		}
+		let all_members = ws.members().collect::<Vec<_>>();
+		workspace_ignore.warn_undeclared("`workspace.metadata.cargo-udeps`", &all_members, &mut config.shell())?;
+		let udeps_config = Config::load(&ws).map_err(config_error)?;
+		let baseline = self.baseline
+			.as_ref()
+			.map(|path| Baseline::load(&config.cwd().join(path)))
+			.transpose()
+			.map_err(config_error)?;
+		// Config files take precedence over `workspace.metadata.cargo-udeps`.
+		let config_options = udeps_config.options().or(metadata_options);
+		let mut configs = metadata_configs;
+		let mut cli_severity = SeverityPolicy::default();
+		for (category, severity) in &self.severity {
+			cli_severity.set(category, *severity);
+		}
+		let severity = cli_severity
+			.or(udeps_config.severity())
+			.or(metadata_severity)
+			.resolve();
+		configs.extend(udeps_config.configs());
+
+		let members = ws
+			.members()
+			.map(|member| (member.package_id(), member))
+			.collect::<HashMap<_, _>>();
+		let package_ignores = ws
+			.members()
+			.map(|member| {
+				let ignore = member
+					.manifest()
+					.custom_metadata()
+					.map::<CargoResult<_>, _>(|package_metadata| {
+						let PackageMetadata {
+							cargo_udeps: PackageMetadataCargoUdeps { ignore },
+						} = package_metadata
+							.clone()
+							.try_into()
+							.with_context(|| format!(
+								"could not parse `package.metadata.cargo-udeps` in `{}`",
+								member.manifest_path().display(),
+							))?;
+						Ok(ignore)
+					})
+					.transpose()?;
+				if let Some(ignore) = &ignore {
+					let source = format!("`package.metadata.cargo-udeps` of `{}`", member.name());
+					ignore.warn_undeclared(&source, &[member], &mut config.shell())?;
+				}
+				Ok((member.package_id(), ignore))
+			})
+			.collect::<CargoResult<HashMap<_, _>>>()
+			.map_err(config_error)?;
+		let manifests = ws
+			.members()
+			.map(|member| Ok((member.package_id(), Manifest::load(member.manifest_path())?)))
+			.collect::<CargoResult<HashMap<_, _>>>()
+			.map_err(config_error)?;
+		let annotation_ignores = manifests
+			.iter()
+			.map(|(&id, manifest)| (id, PackageMetadataCargoUdepsIgnore::from_annotations(manifest)))
+			.collect::<HashMap<_, _>>();
+		// The ignore lists of all sources are merged.
+		let ignores = ws
+			.members()
+			.map(|member| {
+				let ignores = package_ignores[&member.package_id()]
+					.iter()
+					.chain(std::iter::once(&annotation_ignores[&member.package_id()]))
+					.chain(std::iter::once(&workspace_ignore))
+					.chain(udeps_config.ignores(member, &ws))
+					.collect::<Vec<_>>();
+				(member.package_id(), ignores)
+			})
+			.collect::<HashMap<_, _>>();
+
+		let config_sets = if self.config_set.iter().any(|name| name == "all") {
+			if configs.is_empty() {
+				return Err(config_error(anyhow::anyhow!("`--config-set all` was given, but no configurations are defined")));
+			}
+			configs.keys().cloned().collect()
+		} else {
+			self.config_set.clone()
+		};
+		let mut runs = Vec::new();
+		if config_sets.is_empty() {
+			runs.push((None, config_options.clone()));
+		}
+		for name in config_sets {
+			let set_options = configs.get(&name).cloned().ok_or_else(|| {
+				config_error(anyhow::anyhow!("no configuration named `{}` is defined", name))
+			})?;
+			runs.push((Some(name), set_options.or(config_options.clone())));
+		}
+
+		let (options, compile_opts) = self.effective_options(config, config_options)
+			.map_err(config_error)?;
+		// The members selected with `--package`, `--workspace` and `--exclude`
+		let analyzed = compile_opts.spec.get_packages(&ws)
+			.map_err(config_error)?
+			.iter()
+			.map(|package| package.package_id())
+			.collect::<HashSet<_>>();
+		if self.verbose > 0 {
+			config.shell().info(format_args!("effective options: {}", options))?;
+		}
+
+		let mut outcome = Outcome {
+			options,
+			severity,
+			workspace_root : ws.root().to_owned(),
+			manifests,
+			packages : members.iter().map(|(&id, &member)| (id, member.clone())).collect(),
+			toolchain : Some(config.load_global_rustc(Some(&ws))?.verbose_version),
+			show_savings : self.compile_times,
+			..Outcome::default()
+		};
+
+		let mut analyses = Vec::new();
+		let changed = incremental.changed.take();
+		let previous_analyses = std::mem::take(&mut incremental.analyses);
+		let mut messages = messages.filter(|_| self.json_message_format().is_some());
+		let mut emitted = HashSet::new();
+		let run_count = runs.len();
+		for (index, (name, config_options)) in runs.into_iter().enumerate() {
+			let (run_options, mut compile_opts) = self.effective_options(config, config_options)
+				.map_err(config_error)?;
+			if let Some(name) = &name {
+				config.shell().info(format_args!("Analyzing configuration `{}`: {}", name, run_options))?;
+			}
+			// The findings of a member are final once the last run analyzed it
+			let mut on_member = |id, member_analysis :&Analysis| -> CargoResult<()> {
+				let Some(messages) = messages.as_mut().filter(|_| index + 1 == run_count) else {
+					return Ok(());
+				};
+				let runs = analyses
+					.iter()
+					.map(|(name, _, analysis)| (name, analysis))
+					.chain([(&name, member_analysis)])
+					.collect::<Vec<_>>();
+				outcome.print_member_messages(id, &runs, baseline.as_ref(), &mut **messages)?;
+				emitted.insert(id);
+				Ok(())
+			};
+			let analysis = match (&changed, previous_analyses.get(&name)) {
+				(Some(changed), Some(previous)) => {
+					let mut analysis = previous.clone();
+					if !changed.is_empty() {
+						compile_opts.spec = Packages::Packages(changed.iter().map(|id| id.to_spec().to_string()).collect());
+						let changed_analysis = self.analyze(config, &ws, &run_options, &compile_opts, &ignores, &mut on_member)
+							.map_err(|err| CliError::new(err, EXIT_BUILD_FAILED))?;
+						analysis.update(changed_analysis, changed);
+					}
+					analysis
+				},
+				_ => self.analyze(config, &ws, &run_options, &compile_opts, &ignores, &mut on_member)
+					.map_err(|err| CliError::new(err, EXIT_BUILD_FAILED))?,
+			};
+			incremental.analyses.insert(name.clone(), analysis.clone());
+			analyses.push((name, run_options, analysis));
+		}
+
+		let runs = analyses
+			.iter()
+			.map(|(name, _, analysis)| (name, analysis))
+			.collect::<Vec<_>>();
+		if let Some(messages) = &mut messages {
+			// The members that `--watch` didn't analyze again
+			let remaining = runs
+				.last()
+				.into_iter()
+				.flat_map(|(_, analysis)| analysis.verdicts.keys().map(|&(id, _, _)| id))
+				.filter(|id| !emitted.contains(id))
+				.collect::<BTreeSet<_>>();
+			for id in remaining {
+				outcome.print_member_messages(id, &runs, baseline.as_ref(), &mut **messages)?;
+			}
+		}
+		outcome.record(&runs, |_| true, Some(&mut config.shell()))?;
+		outcome.configs = analyses
+			.iter()
+			.filter_map(|(name, options, _)| Some((name.clone()?, options.clone())))
+			.collect();
+
+		if let Some(path) = &self.write_baseline {
+			Baseline::new(&outcome).write(&config.cwd().join(path))?;
+		}
+		if let Some(baseline) = &baseline {
+			baseline.apply(&mut outcome, &analyzed)?;
+		}
+
+		let (has_denied, has_warned) = (outcome.has_unused(Severity::Deny), outcome.has_unused(Severity::Warn));
+		outcome.success = !has_denied
+			&& (outcome.severity.declared_used != Severity::Deny || outcome.declared_used_deps.is_empty());
+
+		if has_denied || has_warned {
+			if analyses.iter().any(|(_, options, _)| !options.all_targets) {
+				outcome.notes.push(NoteCode::OtherTargets);
+
+				if !self.lib
+					&& !self.bins
+					&& !self.examples
+					&& !self.tests
+					&& !self.benches
+					&& self.bin.is_empty()
+					&& self.example.is_empty()
+					&& self.test.is_empty()
+					&& self.bench.is_empty()
+				{
+					outcome.notes.push(NoteCode::EnableAllTargets);
+				}
+			}
+
+			if analyses.iter().any(|(_, _, analysis)| analysis.has_non_lib) {
+				outcome.notes.push(NoteCode::NonLibPackages);
+			}
+
+			outcome.notes.push(NoteCode::FalsePositives);
+			outcome.note = Some(outcome.notes.iter().map(|note| note.human()).collect());
+		}
+
+		Ok(outcome)
+	}
+
+	/// Whether `--message-format` asks for cargo's JSON messages, and how diagnostics in them are rendered.
+	fn json_message_format(&self) -> Option<Rendering> {
+		let formats = self.message_format.iter().map(|format| format.to_ascii_lowercase()).collect::<Vec<_>>();
+		if !formats.iter().any(|format| format.starts_with("json")) {
+			return None;
+		}
+		Some(Rendering {
+			short : formats.iter().any(|format| format == "json-diagnostic-short"),
+			ansi : formats.iter().any(|format| format == "json-diagnostic-rendered-ansi"),
+		})
+	}
+
+	/// The message format of the build, like cargo's `--message-format`.
+	fn cargo_message_format(&self) -> CargoResult<MessageFormat> {
+		let formats = self.message_format.iter().map(|format| format.to_ascii_lowercase()).collect::<Vec<_>>();
+		let kinds = formats.iter().filter(|format| !format.starts_with("json-")).collect::<Vec<_>>();
+		if kinds.len() > 1 || kinds.iter().any(|&kind| kind != "json") && formats.len() > 1 {
+			anyhow::bail!("cannot specify two kinds of `message-format` arguments");
+		}
+		Ok(match self.json_message_format() {
+			Some(Rendering { short, ansi }) => MessageFormat::Json { short, ansi, render_diagnostics : false },
+			None if kinds.iter().any(|&kind| kind == "short") => MessageFormat::Short,
+			None => MessageFormat::Human,
+		})
+	}
+
+	/// Prints the outcome in the requested formats, returning the exit code.
+	fn print(&self, outcome :&Outcome, stdout :impl Write) -> Result<i32, CliError> {
+		let rendering = self.json_message_format();
+		match outcome.options.output {
+			// The rendered diagnostics follow `--message-format`, like those of rustc
+			OutputKind::RustcJson => diagnostic::print_json(outcome, rendering.unwrap_or_default(), stdout)?,
+			output => outcome.print(output, stdout)?,
+		}
+		Ok(if outcome.success { 0 } else { EXIT_UNUSED })
+	}
+
+	/// Merges the command line with `config_options`, which fill in the options that weren't given.
+	fn effective_options(
+		&self,
+		config :&GlobalContext,
+		config_options :ConfigOptions,
+	) -> CargoResult<(EffectiveOptions, CompileOptions)> {
+		// `--release` is an explicit profile too, which the config must not override
+		let profile = self.profile
+			.clone()
+			.or_else(|| self.release.then(|| "release".to_owned()))
+			.or(config_options.profile);
+		let test = match profile.as_deref() {
+			None | Some("release") => false,
 			Some("test") => true,
 			Some(profile) => return Err(anyhow::anyhow!(
-				"unknown profile: `{}`, only `test` is currently supported",
+				"unknown profile: `{}`, only `test` and `release` are currently supported",
 				profile,
 			)),
 		};
-		let mode = UserIntent::Check { test };
-		let pc = ProfileChecking::LegacyTestOnly;
-		let compile_opts = clap_matches.compile_options(config, mode, Some(&ws), pc)?;
-		let requested_kinds = &compile_opts.build_config.requested_kinds;
-		let mut target_data = RustcTargetData::new(&ws, requested_kinds)?;
+		let jobs = self.jobs.as_ref().map(|jobs| match jobs.parse() {
+			Ok(jobs) => JobsConfig::Integer(jobs),
+			Err(_) => JobsConfig::String(jobs.clone()),
+		});
+		let requested_targets = self.target.iter().cloned().collect::<Vec<_>>();
+		let mut build_config = BuildConfig::new(config, jobs, self.keep_going, &requested_targets, UserIntent::Check { test })?;
+		build_config.message_format = self.cargo_message_format()?;
+		build_config.requested_profile = profile.as_deref().unwrap_or("dev").into();
+		let mut compile_opts = CompileOptions {
+			build_config,
+			cli_features : CliFeatures::new_all(false),
+			spec : Packages::from_flags(self.workspace, self.exclude.clone(), self.packages.clone())?,
+			filter : CompileFilter::from_raw_arguments(
+				self.lib,
+				self.bin.clone(),
+				self.bins,
+				self.test.clone(),
+				self.tests,
+				self.example.clone(),
+				self.examples,
+				self.bench.clone(),
+				self.benches,
+				self.all_targets,
+			),
+			target_rustdoc_args : None,
+			target_rustc_args : None,
+			target_rustc_crate_types : None,
+			rustdoc_document_private_items : false,
+			honor_rust_version : None,
+		};
 
-		let cli_features = CliFeatures::from_command_line(
-			&self.features,
-			self.all_features,
-			!self.no_default_features,
+		// Target selection flags on the command line replace `all-targets` from the config.
+		let all_targets = if compile_opts.filter.is_specific() {
+			self.all_targets
+		} else {
+			config_options.all_targets.unwrap_or(false)
+		};
+		if all_targets {
+			compile_opts.filter = CompileFilter::new_all_targets();
+		}
+		let features = self.features.clone().or(config_options.features).unwrap_or_default();
+		let target = match config_options.target {
+			Some(target) if self.target.is_none() => {
+				let target = target.into_vec();
+				compile_opts.build_config.requested_kinds = CompileKind::from_requested_targets(config, &target)?;
+				target
+			},
+			_ => requested_targets,
+		};
+		let backend = self.backend.or(config_options.backend).unwrap_or_default();
+		let output = self.output.or(config_options.output).unwrap_or_default();
+
+		let options = EffectiveOptions {
+			all_targets,
+			features,
+			all_features : self.all_features || config_options.all_features == Some(true),
+			no_default_features : self.no_default_features || config_options.no_default_features == Some(true),
+			profile,
+			target,
+			backend,
+			output,
+		};
+		compile_opts.cli_features = CliFeatures::from_command_line(
+			&options.features,
+			options.all_features,
+			!options.no_default_features,
 		)?;
+		Ok((options, compile_opts))
+	}
+
+	/// Compiles the workspace and finds out which dependencies of the selected packages were used.
+	///
+	/// `on_member` is called with the analysis of each selected member, after the build.
+	fn analyze(
+		&self,
+		config :&GlobalContext,
+		ws :&Workspace<'_>,
+		options :&EffectiveOptions,
+		compile_opts :&CompileOptions,
+		ignores :&HashMap<PackageId, Vec<&PackageMetadataCargoUdepsIgnore>>,
+		on_member :&mut dyn FnMut(PackageId, &Analysis) -> CargoResult<()>,
+	) -> CargoResult<Analysis> {
+		let requested_kinds = &compile_opts.build_config.requested_kinds;
+		let mut target_data = RustcTargetData::new(ws, requested_kinds)?;
+
 		let dry_run = false;
 		let ws_resolve = cargo::ops::resolve_ws_with_opts(
-			&ws,
+			ws,
 			&mut target_data,
 			requested_kinds,
-			&cli_features,
-			&Packages::All(Vec::new()).to_package_id_specs(&ws)?,
+			&compile_opts.cli_features,
+			&Packages::All(Vec::new()).to_package_id_specs(ws)?,
 			HasDevUnits::Yes,
 			ForceAllTargets::No,
 			dry_run,
@@ -306,25 +932,25 @@ impl OptUdeps {
 			})
 			.collect::<CargoResult<HashMap<_, _>>>()?;
 
-		let data = Arc::new(Mutex::new(ExecData::new(&ws)?));
+		let data = Arc::new(Mutex::new(ExecData::new(ws)?));
 		let exec :Arc<dyn Executor + 'static> = Arc::new(Exec { data : data.clone() });
-		cargo::ops::compile_with_exec(&ws, &compile_opts, &exec)?;
+		cargo::ops::compile_with_exec(ws, compile_opts, &exec)?;
 		let data = data.lock().unwrap();
-
 		let mut used_normal_dev_dependencies = HashSet::new();
 		let mut used_build_dependencies = HashSet::new();
-		let mut normal_dependencies = dependency_names
-			.iter()
-			.flat_map(|(&m, d)| d[dependency::DepKind::Normal].non_lib.iter().map(move |&s| (m, s)))
-			.collect::<HashSet<_>>();
-		let mut dev_dependencies = dependency_names
-			.iter()
-			.flat_map(|(&m, d)| d[dependency::DepKind::Development].non_lib.iter().map(move |&s| (m, s)))
-			.collect::<HashSet<_>>();
-		let mut build_dependencies = dependency_names
+		let mut declared_used_normal_dev_dependencies = HashSet::new();
+		let mut declared_used_build_dependencies = HashSet::new();
+		// The targets each dependency was used by, like `lib:foo`
+		let mut normal_dev_used_in = HashMap::<_, BTreeSet<String>>::new();
+		let mut build_used_in = HashMap::<_, BTreeSet<String>>::new();
+		// Maps each dependency to the indices of the `relevant_cmd_infos` it was passed to.
+		let non_lib_dependencies = |kind :dependency::DepKind| dependency_names
 			.iter()
-			.flat_map(|(&m, d)| d[dependency::DepKind::Build].non_lib.iter().map(move |&s| (m, s)))
-			.collect::<HashSet<_>>();
+			.flat_map(|(&m, d)| d[kind].non_lib.iter().map(move |&s| ((m, s), Vec::new())))
+			.collect::<HashMap<_, _>>();
+		let mut normal_dependencies = non_lib_dependencies(dependency::DepKind::Normal);
+		let mut dev_dependencies = non_lib_dependencies(dependency::DepKind::Development);
+		let mut build_dependencies = non_lib_dependencies(dependency::DepKind::Build);
 
 		let mut lib_stem_to_pkg_id = HashMap::new();
 		for cmd_info in data.all_cmd_infos.iter() {
@@ -332,73 +958,64 @@ impl OptUdeps {
 			//println!("lib stem {} -> {}", lib_stem, cmd_info.pkg);
 			lib_stem_to_pkg_id.insert(lib_stem, cmd_info.pkg);
 		}
-		enum BackendData {
-			Depinfo(DepInfo),
-		}
-		for cmd_info in data.relevant_cmd_infos.iter() {
-			let backend_data = match self.backend {
-				Backend::Depinfo => {
-					let depinfo = cmd_info.get_depinfo(&mut config.shell())?;
-					BackendData::Depinfo(depinfo)
-				},
-			};
+		let backend = backend::new(options.backend, self.backend_command.as_deref(), config.cwd())?;
+		let artifacts = Artifacts { by_base_name : &lib_stem_to_pkg_id };
+		let mut sources = HashMap::<_, BTreeSet<_>>::new();
+		for (cmd_index, cmd_info) in data.relevant_cmd_infos.iter().enumerate() {
+			let output = backend.analyze(cmd_info, &artifacts, &mut config.shell())?;
 			// may not be workspace member
 			if let Some(dependency_names) = dependency_names.get(&cmd_info.pkg) {
+				sources
+					.entry(cmd_info.pkg)
+					.or_default()
+					.extend(output.sources.iter().map(|source| ws.root().join(source)));
+				let mut declared_used_names = HashSet::new();
+				for source in &output.sources {
+					// rustc is run from the workspace root
+					let source = match std::fs::read_to_string(ws.root().join(source)) {
+						Ok(source) => source,
+						Err(_) => continue,
+					};
+					declared_used_names.extend(
+						declared_used_crates(&source)
+							.into_iter()
+							// Only count crates that were passed to this compilation
+							.filter(|name| cmd_info.extern_crate_names.contains(name)),
+					);
+				}
 				let collect_names = |
 					dnv :&DependencyNamesValue,
 					used_dependencies: &mut HashSet<(PackageId, InternedString)>,
-					dependencies: &mut HashSet<(PackageId, InternedString)>,
+					used_in: &mut HashMap<(PackageId, InternedString), BTreeSet<String>>,
+					declared_used_dependencies: &mut HashSet<(PackageId, InternedString)>,
+					dependencies: &mut HashMap<(PackageId, InternedString), Vec<usize>>,
 				| {
-					match &backend_data {
-						BackendData::Depinfo(depinfo) => for dep in depinfo.deps_of_depfile()  {
-							let fs = if let Some(fs) = dep.file_stem() {
-								fs
-							} else {
-								continue
-							};
-							let fs :String = match fs.to_str() {
-								Some(v) => v.to_string(),
-								_ => continue,
-							};
-							// The file names are like cratename-hash.rmeta or .rlib,
-							// where "hash" is a hash string that cargo calls "metadata"
-							// internally and computes in its "compute_metadata" function,
-							// and cratename is the snakecased crate name.
-
-							// First, we continue if there is no - in the filename.
-							// it's likely a source file or some other artifact we aren't
-							// interested in. This is obviously only a stupid heuristic.
-							let lib_name = match fs.split_once('-') {
-								None => continue,
-								Some((lib_name, _)) => lib_name
-							};
-
-							// The metadata hash is not available through cargo's api
-							// outside of the Executor trait impl. We do our best to obtain
-							// the hashes from that impl, but the executor is not called
-							// for anything but crates that have to be recompiled.
-							// Thus, any crates that weren't recompiled we don't know the
-							// metadata hash of. So we perform a check: if we know the metadata
-							// hash, we use it, otherwise we don't.
-							// This gives a bit surprising behaviour when re-running
-							// cargo-udeps but at least sometimes the results are more accurate.
-
-							if let Some(pkg_id) = lib_stem_to_pkg_id.get(&fs) {
-								if let Some(dependency_name) = dnv.by_package_id.get(pkg_id) {
-									used_dependencies.insert((cmd_info.pkg, *dependency_name));
-								}
-							} else {
-								// TODO this is a hack as we unconditionally strip the prefix.
-								// It won't work for proc macro crates that start with "lib".
-								// See maybe_lib in the code above.
-								let lib_name = lib_name.strip_prefix("lib").unwrap_or(lib_name);
-								if let Some(dependency_names) = dnv.by_lib_true_snakecased_name.get(lib_name) {
-									for dependency_name in dependency_names {
-										used_dependencies.insert((cmd_info.pkg, *dependency_name));
-									}
+					let mut mark_used = |dependency_name :InternedString| {
+						used_dependencies.insert((cmd_info.pkg, dependency_name));
+						used_in
+							.entry((cmd_info.pkg, dependency_name))
+							.or_default()
+							.insert(format!("{}:{}", cmd_info.target_kind, cmd_info.target_name));
+					};
+					for extern_crate_name in &declared_used_names {
+						if let Some(dependency_name) = dnv.by_extern_crate_name.get(&**extern_crate_name) {
+							declared_used_dependencies.insert((cmd_info.pkg, *dependency_name));
+						}
+					}
+					for used in &output.used {
+						match used {
+							UsedCrate::Package(pkg_id) => if let Some(dependency_name) = dnv.by_package_id.get(pkg_id) {
+								mark_used(*dependency_name);
+							},
+							UsedCrate::LibName(lib_name) => if let Some(dependency_names) = dnv.by_lib_true_snakecased_name.get(&**lib_name) {
+								for dependency_name in dependency_names {
+									mark_used(*dependency_name);
 								}
-							}
-						},
+							},
+							UsedCrate::ExternCrateName(extern_crate_name) => if let Some(dependency_name) = dnv.by_extern_crate_name.get(&**extern_crate_name) {
+								mark_used(*dependency_name);
+							},
+						}
 					}
 
 					for extern_crate_name in &cmd_info.extern_crate_names {
@@ -406,7 +1023,7 @@ impl OptUdeps {
 						// 1. the `lib` that `bin`s, `example`s, and `test`s in the same `Package` depend on
 						// 2. crates bundled with `rustc` such as `proc-macro`
 						if let Some(dependency_name) = dnv.by_extern_crate_name.get(&**extern_crate_name) {
-							dependencies.insert((cmd_info.pkg, *dependency_name));
+							dependencies.entry((cmd_info.pkg, *dependency_name)).or_default().push(cmd_index);
 						}
 					}
 				};
@@ -414,127 +1031,172 @@ impl OptUdeps {
 				collect_names(
 					&dependency_names.normal,
 					&mut used_normal_dev_dependencies,
+					&mut normal_dev_used_in,
+					&mut declared_used_normal_dev_dependencies,
 					&mut normal_dependencies,
 				);
 				collect_names(
 					&dependency_names.development,
 					&mut used_normal_dev_dependencies,
+					&mut normal_dev_used_in,
+					&mut declared_used_normal_dev_dependencies,
 					&mut dev_dependencies,
 				);
 				collect_names(
 					&dependency_names.build,
 					&mut used_build_dependencies,
+					&mut build_used_in,
+					&mut declared_used_build_dependencies,
 					&mut build_dependencies,
 				);
 			}
 		}
 
-		use anyhow::Context;
-		let workspace_ignore = ws
-			.custom_metadata()
-			.map::<CargoResult<_>, _>(|workspace_metadata| {
-				let PackageMetadata {
-					cargo_udeps: PackageMetadataCargoUdeps { ignore },
-				} = workspace_metadata
-					.clone()
-					.try_into()
-					.context("could not parse `workspace.metadata.cargo-udeps`")?;
-					Ok(ignore)
-			})
-			.transpose()?;
-
-		let mut outcome = Outcome::default();
+		let cmd_cfgs = data.relevant_cmd_infos
+			.iter()
+			.map(|cmd_info| cmd_info.target_cfgs(&target_data, requested_kinds))
+			.collect::<Vec<_>>();
 
-		let included_packages = compile_opts.spec.get_packages(&ws)?
+		let all_members = ws.members().map(Package::package_id).collect::<Vec<_>>();
+		let included_packages = compile_opts.spec.get_packages(ws)?
 			.iter()
 			.map(|x|x.package_id())
 			.collect::<HashSet<_>>();
-		for (dependencies, used_dependencies, kind) in &[
-			(&normal_dependencies, &used_normal_dev_dependencies, dependency::DepKind::Normal),
-			(&dev_dependencies, &used_normal_dev_dependencies, dependency::DepKind::Development),
-			(&build_dependencies, &used_build_dependencies, dependency::DepKind::Build),
-		] {
-			for &(id, dependency) in *dependencies {
-				// This package may have been explicitly excluded via flags.
-				if !included_packages.contains(&id) {
-					continue;
-				}
-
-				let ignore = ws_resolve
-					.pkg_set
-					.get_one(id)?
-					.manifest()
-					.custom_metadata()
-					.map::<CargoResult<_>, _>(|package_metadata| {
-						let PackageMetadata {
-							cargo_udeps: PackageMetadataCargoUdeps { ignore },
-						} = package_metadata
-							.clone()
-							.try_into()
-							.context("could not parse `package.metadata.cargo-udeps`")?;
-						Ok(ignore)
-					})
-					.transpose()?;
+		let mut analysis = Analysis {
+			verdicts : HashMap::new(),
+			used_in : HashMap::new(),
+			savings : HashMap::new(),
+			sources : HashMap::new(),
+			has_non_lib : dependency_names.values().any(DependencyNames::has_non_lib),
+		};
+		for member in ws.members() {
+			let member_id = member.package_id();
+			// This package may have been explicitly excluded via flags.
+			if !included_packages.contains(&member_id) {
+				continue;
+			}
+			let mut verdicts = HashMap::new();
+			let mut used_in = HashMap::new();
+			for (dependencies, used_dependencies, declared_used_dependencies, dependencies_used_in, kind) in &[
+				(&normal_dependencies, &used_normal_dev_dependencies, &declared_used_normal_dev_dependencies, &normal_dev_used_in, dependency::DepKind::Normal),
+				(&dev_dependencies, &used_normal_dev_dependencies, &declared_used_normal_dev_dependencies, &normal_dev_used_in, dependency::DepKind::Development),
+				(&build_dependencies, &used_build_dependencies, &declared_used_build_dependencies, &build_used_in, dependency::DepKind::Build),
+			] {
+				for (&(id, dependency), cmd_indices) in *dependencies {
+					if id != member_id {
+						continue;
+					}
 
-				if !used_dependencies.contains(&(id, dependency)) {
-					if ignore.map_or(false, |ignore| ignore.contains(*kind, dependency)) ||
-						workspace_ignore.as_ref().map_or(false, |ignore| ignore.contains(*kind, dependency))
-					{
-						config.shell().info(format_args!("Ignoring `{}` ({:?})", dependency, kind))?;
+					// The marker itself makes rustc load the crate, so check it first
+					let verdict = if declared_used_dependencies.contains(&(id, dependency)) {
+						Verdict::DeclaredUsed
+					} else if used_dependencies.contains(&(id, dependency)) {
+						Verdict::Used
 					} else {
-						outcome
-							.unused_deps
-							.entry(id)
-							.or_insert(OutcomeUnusedDeps::new(packages[&id].manifest_path())?)
-							.unused_deps_mut(*kind)
-							.insert(dependency);
+						let declared_in = cmd_indices
+							.iter()
+							.map(|&i| (&data.relevant_cmd_infos[i], &*cmd_cfgs[i]))
+							.collect::<Vec<_>>();
+						let rule = ignores[&id]
+							.iter()
+							.find_map(|ignore| ignore.find(*kind, dependency, &declared_in));
+						match rule {
+							Some(rule) => Verdict::Ignored { reason : rule.reason().map(str::to_owned) },
+							None => Verdict::Unused,
+						}
+					};
+					if verdict == Verdict::Used
+						&& let Some(targets) = dependencies_used_in.get(&(id, dependency))
+					{
+						used_in.insert((id, *kind, dependency), targets.clone());
 					}
+					verdicts.insert((id, *kind, dependency), verdict);
 				}
 			}
-		}
 
-		outcome.success = outcome
-			.unused_deps
-			.values()
-			.all(|OutcomeUnusedDeps { normal, development, build, .. }| {
-				normal.is_empty() && development.is_empty() && build.is_empty()
-			});
-
-		if !outcome.success {
-			let mut note = "".to_owned();
-
-			if !self.all_targets {
-				note += "Note: These dependencies might be used by other targets.\n";
+			let savings = verdicts
+				.iter()
+				.filter(|(_, verdict)| **verdict == Verdict::Unused)
+				.map(|(&(id, kind, dependency), _)| {
+					let packages = savings::removed_packages(&ws_resolve.targeted_resolve, &all_members, id, kind, dependency);
+					let compile_time = if self.compile_times {
+						savings::compile_time(&packages, &data.compile_times)
+					} else {
+						None
+					};
+					let edges = savings::edges(&ws_resolve.targeted_resolve, &packages);
+					((id, kind, dependency), Savings { packages, edges, compile_time })
+				})
+				.collect();
+
+			let member_analysis = Analysis {
+				verdicts,
+				used_in,
+				savings,
+				sources : sources.remove(&member_id).into_iter().map(|sources| (member_id, sources)).collect(),
+				has_non_lib : dependency_names[&member_id].has_non_lib(),
+			};
+			// The verdicts of this member are final
+			on_member(member_id, &member_analysis)?;
+			analysis.verdicts.extend(member_analysis.verdicts);
+			analysis.used_in.extend(member_analysis.used_in);
+			analysis.savings.extend(member_analysis.savings);
+			analysis.sources.extend(member_analysis.sources);
+		}
+		// Sources of members that weren't selected
+		analysis.sources.extend(sources);
 
-				if !self.lib
-					&& !self.bins
-					&& !self.examples
-					&& !self.tests
-					&& !self.benches
-					&& self.bin.is_empty()
-					&& self.example.is_empty()
-					&& self.test.is_empty()
-					&& self.bench.is_empty()
-				{
-					note += "      To find dependencies that are not used by any target, enable `--all-targets`.\n";
-				}
-			}
+		Ok(analysis)
+	}
+}
 
-			if dependency_names.values().any(DependencyNames::has_non_lib) {
-				note += "Note: Some dependencies are non-library packages.\n";
-				note += "      `cargo-udeps` regards them as unused.\n";
-			}
+/// The verdicts of compiling the workspace once.
+#[derive(Clone)]
+struct Analysis {
+	verdicts :HashMap<(PackageId, dependency::DepKind, InternedString), Verdict>,
+	/// The targets that used each used dependency, like `lib:foo`
+	used_in :HashMap<(PackageId, dependency::DepKind, InternedString), BTreeSet<String>>,
+	/// What removing each unused dependency would save
+	savings :HashMap<(PackageId, dependency::DepKind, InternedString), Savings>,
+	/// The source files of each workspace member, as reported by the backend
+	sources :HashMap<PackageId, BTreeSet<PathBuf>>,
+	has_non_lib :bool,
+}
 
-			note += "Note: They might be false-positive.\n";
-			note += "      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.\n";
-			note += "      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.\n";
+impl Analysis {
+	/// Replaces the results for `members` with those of `analysis`, which analyzed them again.
+	fn update(&mut self, analysis :Analysis, members :&HashSet<PackageId>) {
+		self.verdicts.retain(|(id, _, _), _| !members.contains(id));
+		self.verdicts.extend(analysis.verdicts.into_iter().filter(|((id, _, _), _)| members.contains(id)));
+		self.used_in.retain(|(id, _, _), _| !members.contains(id));
+		self.used_in.extend(analysis.used_in.into_iter().filter(|((id, _, _), _)| members.contains(id)));
+		self.savings.retain(|(id, _, _), _| !members.contains(id));
+		self.savings.extend(analysis.savings.into_iter().filter(|((id, _, _), _)| members.contains(id)));
+		self.sources.retain(|id, _| !members.contains(id));
+		self.sources.extend(analysis.sources.into_iter().filter(|(id, _)| members.contains(id)));
+		self.has_non_lib |= analysis.has_non_lib;
+	}
+}
 
-			outcome.note = Some(note);
-		}
+/// What `--watch` keeps from one iteration to the next.
+#[derive(Default)]
+struct Incremental {
+	/// The analysis of each run, by configuration name
+	analyses :HashMap<Option<String>, Analysis>,
+	/// The members whose sources changed since the analyses, `None` if everything has to be analyzed again
+	changed :Option<HashSet<PackageId>>,
+}
 
-		outcome.print(self.output, stdout)?;
-		Ok(if outcome.success { 0 } else { 1 })
-	}
+/// What an analysis found out about a dependency.
+///
+/// When merging the results of several analyses, the greatest verdict wins.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Verdict {
+	Ignored { reason :Option<String> },
+	Unused,
+	/// Marked as used in the source with `#[cfg(cargo_udeps)]`
+	DeclaredUsed,
+	Used,
 }
 
 struct ExecData {
@@ -543,6 +1205,8 @@ struct ExecData {
 	workspace_members :Vec<PackageId>,
 	relevant_cmd_infos :Vec<CmdInfo>,
 	all_cmd_infos :Vec<CmdInfo>,
+	/// How long the commands of each package took
+	compile_times :HashMap<PackageId, Duration>,
 }
 
 impl ExecData {
@@ -568,6 +1232,7 @@ impl ExecData {
 			workspace_members :ws.members().map(Package::package_id).collect(),
 			relevant_cmd_infos : Vec::new(),
 			all_cmd_infos : Vec::new(),
+			compile_times : HashMap::new(),
 		})
 	}
 }
@@ -581,7 +1246,7 @@ impl Executor for Exec {
 			mode :CompileMode, on_stdout_line :&mut dyn FnMut(&str) -> CargoResult<()>,
 			on_stderr_line :&mut dyn FnMut(&str) -> CargoResult<()>) -> CargoResult<()> {
 
-		let cmd_info = cmd_info(id, target.is_custom_build(), cmd).unwrap_or_else(|e| {
+		let cmd_info = cmd_info(id, target, cmd).unwrap_or_else(|e| {
 			panic!("Couldn't obtain crate info {:?}: {:?}", id, e);
 		});
 
@@ -623,7 +1288,14 @@ impl Executor for Exec {
 			}
 			cmd.env(cargo::CARGO_ENV, &bt.cargo_exe);
 		}
+		if is_workspace_member {
+			// Lets crates mark dependencies as used with `#[cfg(cargo_udeps)]`
+			cmd.arg("--cfg").arg("cargo_udeps");
+			cmd.arg("--check-cfg").arg("cfg(cargo_udeps)");
+		}
+		let start = Instant::now();
 		DefaultExecutor.exec(&cmd, id, target, mode, on_stdout_line, on_stderr_line)?;
+		*self.data.lock().unwrap().compile_times.entry(id).or_default() += start.elapsed();
 		Ok(())
 	}
 	fn force_rebuild(&self, unit :&Unit) -> bool {
@@ -637,11 +1309,15 @@ struct CmdInfo {
 	pkg :PackageId,
 	#[allow(dead_code)]
 	custom_build :bool,
+	target_name :String,
+	target_kind :&'static str,
 	crate_name :String,
 	crate_type :String,
 	extra_filename :String,
 	cap_lints_allow :bool,
 	out_dir :String,
+	compile_target :Option<String>,
+	cfgs :Vec<Cfg>,
 	extern_crate_names :HashSet<String>,
 }
 
@@ -655,6 +1331,19 @@ impl CmdInfo {
 		};
 		maybe_lib.to_owned() + &self.crate_name + &self.extra_filename
 	}
+	/// The cfgs this command was compiled with: those of its compile kind
+	/// plus the ones passed via `--cfg`, e.g. enabled features.
+	fn target_cfgs(&self, target_data :&RustcTargetData<'_>, requested_kinds :&[CompileKind]) -> Vec<Cfg> {
+		let kind = self.compile_target
+			.as_deref()
+			.and_then(|compile_target| requested_kinds.iter().copied().find(|kind| {
+				matches!(kind, CompileKind::Target(t) if t.rustc_target() == compile_target)
+			}))
+			.unwrap_or(CompileKind::Host);
+		let mut cfgs = target_data.cfg(kind).to_vec();
+		cfgs.extend(self.cfgs.iter().cloned());
+		cfgs
+	}
 	fn get_depinfo_filename(&self) -> String {
 		self.crate_name.clone() + &self.extra_filename + ".d"
 	}
@@ -692,12 +1381,188 @@ impl DepInfo {
 	}
 }
 
-// Bases on function with same name from cargo source src/cargo/core/compiler/fingerprint.rs
-/// Parse the `.d` dep-info file generated by rustc.
+/// Finds the crates marked as intentionally used in the given source,
+/// with `#[cfg(cargo_udeps)] use foo as _;` or `#[cfg(cargo_udeps)] extern crate foo;`.
 ///
-/// Result is a Vec of `(target, prerequisites)` tuples where `target` is the
-/// rule name, and `prerequisites` is a list of files that it depends on.
-fn parse_rustc_dep_info(rustc_dep_info :&Path) -> CargoResult<Vec<(String, Vec<String>)>> {
+/// Any `cfg` predicate that is true with only `cargo_udeps` set and false with no cfg set
+/// is a marker, like `#[cfg( cargo_udeps )]` or `#[cfg(all(cargo_udeps, not(test)))]`.
+/// Other attributes may follow the marker, and the item may have a visibility like `pub`.
+/// Markers in comments and string literals don't count.
+fn declared_used_crates(source :&str) -> Vec<String> {
+	let code = blank_comments_and_literals(source);
+	let mut names = Vec::new();
+	let mut rest = &*code;
+	while let Some(pos) = rest.find('#') {
+		rest = &rest[pos + 1..];
+		let Some((attribute, after)) = split_attribute(rest) else {
+			continue;
+		};
+		if !is_marker(attribute) {
+			continue;
+		}
+		rest = after;
+		let mut item = after;
+		while let Some((_, after)) = item.trim_start().strip_prefix('#').and_then(split_attribute) {
+			item = after;
+		}
+		names.extend(marked_crate(item));
+	}
+	names
+}
+
+/// Splits an attribute like `[cfg(cargo_udeps)]` off the start of `s`, returning its content and the rest.
+fn split_attribute(s :&str) -> Option<(&str, &str)> {
+	let s = s.trim_start().strip_prefix('[')?;
+	let mut depth = 0;
+	for (i, c) in s.char_indices() {
+		match c {
+			'[' | '(' | '{' => depth += 1,
+			']' if depth == 0 => return Some((&s[..i], &s[i + 1..])),
+			']' | ')' | '}' => depth -= 1,
+			_ => {},
+		}
+	}
+	None
+}
+
+/// Whether the content of an attribute is a `cfg` that only holds with `cargo_udeps`.
+fn is_marker(attribute :&str) -> bool {
+	let predicate = attribute
+		.trim()
+		.strip_prefix("cfg")
+		.and_then(|s| s.trim_start().strip_prefix('('))
+		.and_then(|s| s.strip_suffix(')'));
+	let cargo_udeps = "cargo_udeps".parse::<Cfg>().expect("a valid cfg");
+	predicate
+		.and_then(|predicate| predicate.parse::<CfgExpr>().ok())
+		.is_some_and(|expr| expr.matches(&[cargo_udeps]) && !expr.matches(&[]))
+}
+
+/// The crate that the `use` or `extern crate` item at the start of `item` refers to.
+fn marked_crate(item :&str) -> Option<String> {
+	fn strip_keyword<'a>(s :&'a str, keyword :&str) -> Option<&'a str> {
+		s.trim_start()
+			.strip_prefix(keyword)
+			.filter(|rest| !rest.starts_with(|c :char| c.is_alphanumeric() || c == '_'))
+	}
+
+	let mut item = &item[..item.find(';')?];
+	if let Some(rest) = strip_keyword(item, "pub") {
+		item = rest.trim_start();
+		// Like `pub(crate)`
+		if let Some(rest) = item.strip_prefix('(') {
+			item = &rest[rest.find(')')? + 1..];
+		}
+	}
+	let path = match strip_keyword(item, "use") {
+		Some(path) => path,
+		None => strip_keyword(strip_keyword(item, "extern")?, "crate")?,
+	};
+	let name = path
+		.trim_start()
+		.trim_start_matches("::")
+		.trim_start()
+		.split(|c :char| !(c.is_alphanumeric() || c == '_'))
+		.next()?;
+	(!name.is_empty()).then(|| name.to_owned())
+}
+
+/// Replaces the comments and the string and character literals of Rust source with spaces.
+///
+/// Line breaks are kept, and so are the byte offsets of everything else.
+fn blank_comments_and_literals(source :&str) -> String {
+	let bytes = source.as_bytes();
+	let is_ident = |b :u8| b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii();
+	let mut code = bytes.to_vec();
+	let mut i = 0;
+	while i < bytes.len() {
+		let start = i;
+		match (bytes[i], bytes.get(i + 1)) {
+			(b'/', Some(b'/')) => {
+				i = bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |end| i + end);
+			},
+			(b'/', Some(b'*')) => {
+				// Block comments nest
+				let mut depth = 0;
+				while i < bytes.len() {
+					if bytes[i..].starts_with(b"/*") {
+						depth += 1;
+						i += 2;
+					} else if bytes[i..].starts_with(b"*/") {
+						depth -= 1;
+						i += 2;
+						if depth == 0 {
+							break;
+						}
+					} else {
+						i += 1;
+					}
+				}
+			},
+			(b'"', _) => {
+				i += 1;
+				while i < bytes.len() && bytes[i] != b'"' {
+					i += if bytes[i] == b'\\' { 2 } else { 1 };
+				}
+				i += 1;
+			},
+			// Raw strings like `r#"..."#` and `br"..."`
+			(b'r', _) if start == 0
+				|| !is_ident(bytes[start - 1])
+				|| (bytes[start - 1] == b'b' && (start == 1 || !is_ident(bytes[start - 2]))) =>
+			{
+				let hashes = bytes[i + 1..].iter().take_while(|&&b| b == b'#').count();
+				if bytes.get(i + 1 + hashes) != Some(&b'"') {
+					i += 1;
+					continue;
+				}
+				let mut terminator = vec![b'"'];
+				terminator.extend(std::iter::repeat_n(b'#', hashes));
+				i += 2 + hashes;
+				i = bytes[i..]
+					.windows(terminator.len())
+					.position(|window| window == terminator)
+					.map_or(bytes.len(), |end| i + end + terminator.len());
+			},
+			(b'\'', Some(b'\\')) => {
+				// Skips the escaped character, which may be a quote like in `'\''`
+				i += 3;
+				while i < bytes.len() && bytes[i] != b'\'' {
+					i += 1;
+				}
+				i += 1;
+			},
+			(b'\'', Some(_)) => {
+				// A character literal, unless it is a lifetime like `'a`
+				let len = source[i + 1..].chars().next().map_or(1, char::len_utf8);
+				if bytes.get(i + 1 + len) != Some(&b'\'') {
+					i += 1;
+					continue;
+				}
+				i += 2 + len;
+			},
+			_ => {
+				i += 1;
+				continue;
+			},
+		}
+		let end = i.min(bytes.len());
+		for b in &mut code[start..end] {
+			if *b != b'\n' {
+				*b = b' ';
+			}
+		}
+		i = end;
+	}
+	String::from_utf8(code).expect("only whole characters are replaced")
+}
+
+// Bases on function with same name from cargo source src/cargo/core/compiler/fingerprint.rs
+/// Parse the `.d` dep-info file generated by rustc.
+///
+/// Result is a Vec of `(target, prerequisites)` tuples where `target` is the
+/// rule name, and `prerequisites` is a list of files that it depends on.
+fn parse_rustc_dep_info(rustc_dep_info :&Path) -> CargoResult<Vec<(String, Vec<String>)>> {
 	let contents = std::fs::read_to_string(rustc_dep_info)?;
 	contents
 		.lines()
@@ -723,13 +1588,15 @@ fn parse_rustc_dep_info(rustc_dep_info :&Path) -> CargoResult<Vec<(String, Vec<S
 		.collect()
 }
 
-fn cmd_info(id :PackageId, custom_build :bool, cmd :&ProcessBuilder) -> CargoResult<CmdInfo> {
+fn cmd_info(id :PackageId, target :&Target, cmd :&ProcessBuilder) -> CargoResult<CmdInfo> {
 	let mut args_iter = cmd.get_args();
 	let mut crate_name = None;
 	let mut crate_type = None;
 	let mut extra_filename = None;
 	let mut cap_lints_allow = false;
 	let mut out_dir = None;
+	let mut compile_target = None;
+	let mut cfgs = Vec::new();
 	let mut extern_crate_names = HashSet::new();
 	while let Some(v) = args_iter.next() {
 		if v == "--extern" {
@@ -768,6 +1635,17 @@ fn cmd_info(id :PackageId, custom_build :bool, cmd :&ProcessBuilder) -> CargoRes
 					.expect("non-utf8 crate names not supported")
 					.to_owned());
 			}
+		} else if v == "--target" {
+			if let Some(t) = args_iter.next() {
+				compile_target = Some(t.to_str()
+					.expect("non-utf8 target triples not supported")
+					.to_owned());
+			}
+		} else if v == "--cfg" {
+			if let Some(c) = args_iter.next() {
+				let c = c.to_str().expect("non-utf8 args not supported atm");
+				cfgs.push(c.parse::<Cfg>()?);
+			}
 		} else if v == "-C" {
 			if let Some(arg) = args_iter.next() {
 				let arg = arg.to_str().expect("non-utf8 args not supported atm");
@@ -788,16 +1666,33 @@ fn cmd_info(id :PackageId, custom_build :bool, cmd :&ProcessBuilder) -> CargoRes
 
 	Ok(CmdInfo {
 		pkg,
-		custom_build,
+		custom_build : target.is_custom_build(),
+		target_name : target.name().to_owned(),
+		target_kind : target_kind_name(target.kind()),
 		crate_name,
 		crate_type,
 		extra_filename,
 		cap_lints_allow,
 		out_dir,
+		compile_target,
+		cfgs,
 		extern_crate_names,
 	})
 }
 
+/// The names of the target kinds as they are used by the `--lib`, `--bin`, ...
+/// flags and by scoped ignore rules.
+fn target_kind_name(kind :&TargetKind) -> &'static str {
+	match kind {
+		TargetKind::Lib(_) => "lib",
+		TargetKind::Bin => "bin",
+		TargetKind::Test => "test",
+		TargetKind::Bench => "bench",
+		TargetKind::ExampleLib(_) | TargetKind::ExampleBin => "example",
+		TargetKind::CustomBuild => "build-script",
+	}
+}
+
 #[derive(Debug, Default)]
 struct DependencyNames {
 	normal: DependencyNamesValue,
@@ -935,6 +1830,28 @@ struct DependencyNamesValue {
 	non_lib :HashSet<InternedString>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct WorkspaceMetadata {
+	#[serde(default)]
+	cargo_udeps: WorkspaceMetadataCargoUdeps,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceMetadataCargoUdeps {
+	#[serde(flatten)]
+	options: ConfigOptions,
+	#[serde(default)]
+	configs: BTreeMap<String, ConfigOptions>,
+	#[serde(default)]
+	severity: SeverityPolicy,
+	#[serde(default)]
+	ignore: PackageMetadataCargoUdepsIgnore,
+	/// `deny_unknown_fields` doesn't work together with `flatten`.
+	#[serde(flatten)]
+	unknown: BTreeMap<String, toml::Value>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct PackageMetadata {
@@ -943,29 +1860,257 @@ struct PackageMetadata {
 }
 
 #[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct PackageMetadataCargoUdeps {
 	#[serde(default)]
 	ignore: PackageMetadataCargoUdepsIgnore,
 }
 
 #[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
 struct PackageMetadataCargoUdepsIgnore {
 	#[serde(default)]
-	normal: HashSet<String>,
+	normal: Vec<IgnoreRule>,
 	#[serde(default)]
-	development: HashSet<String>,
+	development: Vec<IgnoreRule>,
 	#[serde(default)]
-	build: HashSet<String>,
+	build: Vec<IgnoreRule>,
 }
 
 impl PackageMetadataCargoUdepsIgnore {
-	fn contains(&self, kind: dependency::DepKind, name_in_toml: InternedString) -> bool {
+	/// The ignore list made of the `# udeps: ignore` annotations in a manifest.
+	fn from_annotations(manifest: &Manifest) -> Self {
+		let mut this = Self::default();
+		for dep in &manifest.dependencies {
+			if let Some(annotation) = &dep.ignore {
+				this.rules_mut(dep.kind).push(IgnoreRule::Table(IgnoreRuleTable {
+					name: dep.name_in_toml.clone(),
+					reason: annotation.reason.clone(),
+					..IgnoreRuleTable::default()
+				}));
+			}
+		}
+		this
+	}
+
+	fn rules_mut(&mut self, kind: dependency::DepKind) -> &mut Vec<IgnoreRule> {
+		match kind {
+			dependency::DepKind::Normal => &mut self.normal,
+			dependency::DepKind::Development => &mut self.development,
+			dependency::DepKind::Build => &mut self.build,
+		}
+	}
+
+	/// The rule ignoring an unused dependency, given the commands that
+	/// declared it.
+	fn find(
+		&self,
+		kind: dependency::DepKind,
+		name_in_toml: InternedString,
+		declared_in: &[(&CmdInfo, &[Cfg])],
+	) -> Option<&IgnoreRule> {
 		match kind {
 			dependency::DepKind::Normal => &self.normal,
 			dependency::DepKind::Development => &self.development,
 			dependency::DepKind::Build => &self.build,
 		}
-		.contains(&*name_in_toml)
+		.iter()
+		.find(|rule| rule.matches(name_in_toml, declared_in))
+	}
+
+	/// Warns about entries that name dependencies which none of `packages` declares under that kind.
+	fn warn_undeclared(&self, source: &str, packages: &[&Package], shell: &mut Shell) -> CargoResult<()> {
+		for (kind, rules) in [
+			(dependency::DepKind::Normal, &self.normal),
+			(dependency::DepKind::Development, &self.development),
+			(dependency::DepKind::Build, &self.build),
+		] {
+			for rule in rules {
+				let declared_kinds = packages
+					.iter()
+					.flat_map(|package| package.dependencies())
+					.filter(|dep| dep.name_in_toml() == rule.name())
+					.map(|dep| dep.kind())
+					.collect::<BTreeSet<_>>();
+				if declared_kinds.contains(&kind) {
+					continue;
+				}
+				let mut msg = format!(
+					"{} ignores `{}` as a {} dependency, but it isn't declared as one",
+					source,
+					rule.name(),
+					kind_name(kind),
+				);
+				if !declared_kinds.is_empty() {
+					let declared_kinds = declared_kinds.into_iter().map(kind_name).collect::<Vec<_>>();
+					write!(msg, " (it is a {} dependency)", declared_kinds.join(" and ")).unwrap();
+				}
+				shell.warn(msg)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// An entry of an ignore list: either a plain dependency name or a table
+/// like `{ name = "jemallocator", target = "bin:server" }`.
+#[derive(Debug)]
+enum IgnoreRule {
+	Name(String),
+	Table(IgnoreRuleTable),
+}
+
+impl<'de> Deserialize<'de> for IgnoreRule {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct Visitor;
+
+		impl<'de> serde::de::Visitor<'de> for Visitor {
+			type Value = IgnoreRule;
+
+			fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				f.write_str(r#"a dependency name or a table like `{ name = "..", target = ".." }`"#)
+			}
+
+			fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<IgnoreRule, E> {
+				Ok(IgnoreRule::Name(v.to_owned()))
+			}
+
+			fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<IgnoreRule, A::Error> {
+				let deserializer = serde::de::value::MapAccessDeserializer::new(map);
+				IgnoreRuleTable::deserialize(deserializer).map(IgnoreRule::Table)
+			}
+		}
+
+		deserializer.deserialize_any(Visitor)
+	}
+}
+
+impl IgnoreRule {
+	fn name(&self) -> &str {
+		match self {
+			IgnoreRule::Name(name) => name,
+			IgnoreRule::Table(rule) => &rule.name,
+		}
+	}
+
+	fn reason(&self) -> Option<&str> {
+		match self {
+			IgnoreRule::Name(_) => None,
+			IgnoreRule::Table(rule) => rule.reason.as_deref(),
+		}
+	}
+
+	fn matches(&self, name_in_toml: InternedString, declared_in: &[(&CmdInfo, &[Cfg])]) -> bool {
+		match self {
+			IgnoreRule::Name(name) => name == &*name_in_toml,
+			// A scoped rule must hold for every command the dependency was passed to,
+			// so that it can't hide the dependency being unused in some other target.
+			IgnoreRule::Table(rule) if rule.is_scoped() => {
+				rule.name == *name_in_toml
+					&& !declared_in.is_empty()
+					&& declared_in.iter().all(|(cmd_info, cfgs)| rule.matches(cmd_info, cfgs))
+			},
+			IgnoreRule::Table(rule) => rule.name == *name_in_toml,
+		}
+	}
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct IgnoreRuleTable {
+	name: String,
+	#[serde(default)]
+	reason: Option<String>,
+	/// `kind:name` (e.g. `bin:server`) or just the name of a target.
+	#[serde(default, deserialize_with = "deserialize_target")]
+	target: Option<String>,
+	#[serde(default, deserialize_with = "deserialize_target_kind")]
+	target_kind: Option<String>,
+	#[serde(default)]
+	feature: Option<String>,
+	#[serde(default, deserialize_with = "deserialize_cfg_expr")]
+	cfg: Option<CfgExpr>,
+}
+
+impl IgnoreRuleTable {
+	fn is_scoped(&self) -> bool {
+		self.target.is_some() || self.target_kind.is_some() || self.feature.is_some() || self.cfg.is_some()
+	}
+
+	fn matches(&self, cmd_info: &CmdInfo, cfgs: &[Cfg]) -> bool {
+		self.target.as_deref().is_none_or(|target| match target.split_once(':') {
+			Some((kind, name)) => kind == cmd_info.target_kind && name == cmd_info.target_name,
+			None => target == cmd_info.target_name,
+		})
+			&& self.target_kind.as_deref().is_none_or(|kind| kind == cmd_info.target_kind)
+			&& self.feature.as_deref().is_none_or(|feature| cfgs.iter().any(|cfg| {
+				matches!(cfg, Cfg::KeyPair(key, value) if key.as_str() == "feature" && value == feature)
+			}))
+			&& self.cfg.as_ref().is_none_or(|cfg| cfg.matches(cfgs))
+	}
+}
+
+const TARGET_KINDS: &[&str] = &["lib", "bin", "example", "test", "bench", "build-script"];
+
+fn check_target_kind<E: serde::de::Error>(kind: &str) -> Result<(), E> {
+	if TARGET_KINDS.contains(&kind) {
+		Ok(())
+	} else {
+		Err(E::unknown_variant(kind, TARGET_KINDS))
+	}
+}
+
+fn deserialize_target<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+	let target = String::deserialize(deserializer)?;
+	if let Some((kind, _)) = target.split_once(':') {
+		check_target_kind(kind)?;
+	}
+	Ok(Some(target))
+}
+
+fn deserialize_target_kind<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+	let kind = String::deserialize(deserializer)?;
+	check_target_kind(&kind)?;
+	Ok(Some(kind))
+}
+
+/// Accepts both `cfg(unix)` (like in `[target.'cfg(unix)'.dependencies]`) and `unix`.
+fn deserialize_cfg_expr<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<CfgExpr>, D::Error> {
+	let s = String::deserialize(deserializer)?;
+	let expr = s
+		.strip_prefix("cfg(")
+		.and_then(|s| s.strip_suffix(')'))
+		.unwrap_or(&s);
+	expr.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+/// The options used for the analysis, after merging the command line with the configuration.
+#[derive(Clone, Default, Debug, Serialize)]
+struct EffectiveOptions {
+	all_targets: bool,
+	features: Vec<String>,
+	all_features: bool,
+	no_default_features: bool,
+	profile: Option<String>,
+	target: Vec<String>,
+	backend: Backend,
+	output: OutputKind,
+}
+
+impl fmt::Display for EffectiveOptions {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"all-targets={} features={:?} all-features={} no-default-features={} profile={} target={:?} backend={:?} output={:?}",
+			self.all_targets,
+			self.features,
+			self.all_features,
+			self.no_default_features,
+			self.profile.as_deref().unwrap_or("dev"),
+			self.target,
+			self.backend,
+			self.output,
+		)
 	}
 }
 
@@ -974,6 +2119,54 @@ struct Outcome {
 	success: bool,
 	unused_deps: BTreeMap<PackageId, OutcomeUnusedDeps>,
 	note: Option<String>,
+	/// Only in `--output json-v2`, like the effective severities and configurations.
+	#[serde(skip)]
+	options: EffectiveOptions,
+	#[serde(skip)]
+	severity: Severities,
+	#[serde(skip)]
+	configs: BTreeMap<String, EffectiveOptions>,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	ignored_deps: BTreeMap<PackageId, OutcomeIgnoredDeps>,
+	/// Dependencies marked as used with `#[cfg(cargo_udeps)]`.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	declared_used_deps: BTreeMap<PackageId, OutcomeUnusedDeps>,
+	/// Findings that are accepted by `--baseline`.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	baselined_deps: BTreeMap<PackageId, OutcomeUnusedDeps>,
+	/// Entries of `--baseline` that are no longer found.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	fixed_baseline: BTreeMap<PackageId, OutcomeUnusedDeps>,
+	#[serde(skip)]
+	workspace_root: PathBuf,
+	/// The manifests of the workspace members, for the locations of the findings.
+	#[serde(skip)]
+	manifests: HashMap<PackageId, Manifest>,
+	#[serde(skip)]
+	packages: HashMap<PackageId, Package>,
+	#[serde(skip)]
+	notes: Vec<NoteCode>,
+	/// The output of `rustc -vV`
+	#[serde(skip)]
+	toolchain: Option<String>,
+	/// The targets that used each used dependency, like `lib:foo`.
+	#[serde(skip)]
+	used_in: HashMap<(PackageId, dependency::DepKind, InternedString), BTreeSet<String>>,
+	/// What removing each unused dependency would save.
+	#[serde(skip)]
+	savings: HashMap<(PackageId, dependency::DepKind, InternedString), Savings>,
+	/// With `--compile-times`, the human output shows the `savings` too.
+	#[serde(skip)]
+	show_savings: bool,
+	/// The unused dependencies with the `allow` severity.
+	#[serde(skip)]
+	allowed_deps: BTreeSet<(PackageId, dependency::DepKind, InternedString)>,
+	/// The dependencies without a reported finding.
+	#[serde(skip)]
+	passed_deps: BTreeSet<(PackageId, dependency::DepKind, InternedString)>,
+	/// The source files of each workspace member.
+	#[serde(skip)]
+	sources: HashMap<PackageId, BTreeSet<PathBuf>>,
 }
 
 impl Outcome {
@@ -981,43 +2174,189 @@ impl Outcome {
 		match output {
 			OutputKind::Human => self.print_human(stdout),
 			OutputKind::Json => self.print_json(stdout),
+			OutputKind::JsonV2 => report::print(self, stdout),
+			OutputKind::Sarif => sarif::print(self, stdout),
+			OutputKind::Junit => junit::print(self, stdout),
+			OutputKind::Diagnostic => diagnostic::print(self, stdout),
+			OutputKind::RustcJson => diagnostic::print_json(self, Rendering::default(), stdout),
+			OutputKind::Markdown => markdown::print(self, stdout),
+			OutputKind::Dot => dot::print(self, stdout),
+			OutputKind::Html => html::print(self, stdout),
 		}
 	}
 
-	fn print_human(&self, mut stdout: impl Write) -> io::Result<()> {
-		if self.success {
-			writeln!(stdout, "All deps seem to have been used.")?;
-		} else {
-			writeln!(stdout, "unused dependencies:")?;
+	/// The direct dependencies declared by the members, with their status in the outcome.
+	///
+	/// Entries for several platforms are merged.
+	fn declared_dependencies(&self) -> BTreeMap<(PackageId, dependency::DepKind, InternedString), DeclaredDependency> {
+		let contains = |deps: Option<&OutcomeUnusedDeps>, kind, name_in_toml| {
+			deps.is_some_and(|deps| deps.unused_deps(kind).contains(&name_in_toml))
+		};
+		let mut declared = BTreeMap::new();
+		for (&id, package) in &self.packages {
+			for dep in package.dependencies() {
+				let (kind, name_in_toml) = (dep.kind(), dep.name_in_toml());
+				let status = if contains(self.unused_deps.get(&id), kind, name_in_toml) {
+					DependencyStatus::Unused
+				} else if contains(self.baselined_deps.get(&id), kind, name_in_toml) {
+					DependencyStatus::Baselined
+				} else if self.allowed_deps.contains(&(id, kind, name_in_toml)) {
+					DependencyStatus::Allowed
+				} else if self.ignored_deps.get(&id).is_some_and(|deps| deps.ignored_deps(kind).contains_key(&name_in_toml)) {
+					DependencyStatus::Ignored
+				} else if contains(self.declared_used_deps.get(&id), kind, name_in_toml) {
+					DependencyStatus::DeclaredUsed
+				} else if self.passed_deps.contains(&(id, kind, name_in_toml)) {
+					DependencyStatus::Used
+				} else {
+					DependencyStatus::NotEvaluated
+				};
+				declared.insert((id, kind, name_in_toml), DeclaredDependency {
+					package_name: dep.package_name(),
+					status,
+				});
+			}
+		}
+		declared
+	}
 
-			for (member, OutcomeUnusedDeps { normal, development, build, .. }) in &self.unused_deps {
-				fn edge_and_joint(p: bool) -> (char, char) {
-					if p {
-						(' ', '└')
-					} else {
-						('│', '├')
-					}
+	/// Merges the analyses of the runs for the members that `include` accepts.
+	///
+	/// A dependency is only unused if no run used it.
+	/// Ignoring it in one run doesn't hide it being unused in another run.
+	fn record(
+		&mut self,
+		runs: &[(&Option<String>, &Analysis)],
+		include: impl Fn(PackageId) -> bool,
+		mut shell: Option<&mut Shell>,
+	) -> CargoResult<()> {
+		let mut verdicts = BTreeMap::<_, (Verdict, BTreeSet<String>)>::new();
+		for (name, analysis) in runs {
+			for (&key, verdict) in analysis.verdicts.iter().filter(|((id, _, _), _)| include(*id)) {
+				let (merged, evaluated_in) = verdicts.entry(key).or_insert((verdict.clone(), BTreeSet::new()));
+				if verdict > merged {
+					*merged = verdict.clone();
 				}
+				evaluated_in.extend((*name).clone());
+			}
+		}
 
-				writeln!(stdout, "`{}`", member)?;
-
-				for (deps, (edge, joint), prefix) in &[
-					(normal, edge_and_joint(development.is_empty() && build.is_empty()), ""),
-					(development, edge_and_joint(build.is_empty()), "dev-"),
-					(build, (' ', '└'), "build-"),
-				] {
-					if !deps.is_empty() {
-						writeln!(stdout, "{}─── {}dependencies", joint, prefix)?;
-						let mut deps = deps.iter().peekable();
-						while let Some(dep) = deps.next() {
-							let joint = if deps.peek().is_some() {
-								'├'
-							} else {
-								'└'
-							};
-							writeln!(stdout, "{}    {}─── {:?}", edge, joint, dep)?;
+		// Configurations may pull in different packages, report the largest savings.
+		for (_, analysis) in runs {
+			for (&key, analysis_savings) in analysis.savings.iter().filter(|((id, _, _), _)| include(*id)) {
+				let merged = self.savings.entry(key).or_default();
+				if analysis_savings.packages.len() > merged.packages.len() {
+					*merged = analysis_savings.clone();
+				}
+			}
+			for (&key, targets) in analysis.used_in.iter().filter(|((id, _, _), _)| include(*id)) {
+				self.used_in.entry(key).or_default().extend(targets.iter().cloned());
+			}
+			for (&id, member_sources) in analysis.sources.iter().filter(|(id, _)| include(**id)) {
+				self.sources.entry(id).or_default().extend(member_sources.iter().cloned());
+			}
+		}
+
+		for ((id, kind, dependency), (verdict, evaluated_in)) in verdicts {
+			match verdict {
+				Verdict::Used => {
+					self.passed_deps.insert((id, kind, dependency));
+				},
+				Verdict::DeclaredUsed if self.severity.declared_used == Severity::Allow => {
+					self.passed_deps.insert((id, kind, dependency));
+				},
+				Verdict::Unused if self.severity.unused(kind) == Severity::Allow => {
+					if let Some(shell) = shell.as_mut() {
+						shell.info(format_args!("Allowing unused `{}` ({:?})", dependency, kind))?;
+					}
+					self.allowed_deps.insert((id, kind, dependency));
+					self.passed_deps.insert((id, kind, dependency));
+				},
+				Verdict::Ignored { reason } => {
+					if let Some(shell) = shell.as_mut() {
+						match &reason {
+							Some(reason) => shell.info(format_args!("Ignoring `{}` ({:?}): {}", dependency, kind, reason))?,
+							None => shell.info(format_args!("Ignoring `{}` ({:?})", dependency, kind))?,
 						}
 					}
+					self.ignored_deps
+						.entry(id)
+						.or_default()
+						.ignored_deps_mut(kind)
+						.insert(dependency, reason);
+				},
+				Verdict::DeclaredUsed => {
+					let declared_used_deps = match self.declared_used_deps.entry(id) {
+						btree_map::Entry::Occupied(entry) => entry.into_mut(),
+						btree_map::Entry::Vacant(entry) => {
+							entry.insert(OutcomeUnusedDeps::new(self.packages[&id].manifest_path())?)
+						},
+					};
+					declared_used_deps.unused_deps_mut(kind).insert(dependency);
+				},
+				Verdict::Unused => {
+					let unused_deps = match self.unused_deps.entry(id) {
+						btree_map::Entry::Occupied(entry) => entry.into_mut(),
+						btree_map::Entry::Vacant(entry) => {
+							entry.insert(OutcomeUnusedDeps::new(self.packages[&id].manifest_path())?)
+						},
+					};
+					unused_deps.unused_deps_mut(kind).insert(dependency);
+					if !evaluated_in.is_empty() {
+						unused_deps
+							.evaluated_in
+							.entry(kind_name(kind))
+							.or_default()
+							.insert(dependency, evaluated_in);
+					}
+				},
+			}
+		}
+		Ok(())
+	}
+
+	/// Prints the `--message-format json` messages of the member `id`, whose analysis is done.
+	fn print_member_messages(
+		&self,
+		id: PackageId,
+		runs: &[(&Option<String>, &Analysis)],
+		baseline: Option<&Baseline>,
+		messages: &mut dyn Write,
+	) -> CargoResult<()> {
+		let mut outcome = Outcome {
+			severity: self.severity.clone(),
+			workspace_root: self.workspace_root.clone(),
+			manifests: self.manifests.get(&id).map(|manifest| (id, manifest.clone())).into_iter().collect(),
+			packages: self.packages.get(&id).map(|package| (id, package.clone())).into_iter().collect(),
+			..Outcome::default()
+		};
+		outcome.record(runs, |member| member == id, None)?;
+		if let Some(baseline) = baseline {
+			baseline.apply(&mut outcome, &HashSet::from([id]))?;
+		}
+		report::print_messages(&outcome, messages)?;
+		Ok(())
+	}
+
+	fn has_unused(&self, severity: Severity) -> bool {
+		self.unused_deps
+			.values()
+			.any(|deps| deps.any_kind(|kind| self.severity.unused(kind) == severity))
+	}
+
+	fn print_human(&self, mut stdout: impl Write) -> io::Result<()> {
+		if !self.has_unused(Severity::Deny) && !self.has_unused(Severity::Warn) {
+			writeln!(stdout, "All deps seem to have been used.")?;
+		} else {
+			let no_savings = HashMap::new();
+			let savings = if self.show_savings { &self.savings } else { &no_savings };
+			for (severity, header) in [
+				(Severity::Deny, "unused dependencies:"),
+				(Severity::Warn, "unused dependencies (warning):"),
+			] {
+				if self.has_unused(severity) {
+					writeln!(stdout, "{}", header)?;
+					print_deps_tree(&self.unused_deps, |kind| self.severity.unused(kind) == severity, savings, &mut stdout)?;
 				}
 			}
 
@@ -1025,6 +2364,24 @@ impl Outcome {
 				write!(stdout, "{}", note)?;
 			}
 		}
+		if !self.declared_used_deps.is_empty() {
+			match self.severity.declared_used {
+				Severity::Deny => writeln!(stdout, "dependencies declared as used (error):")?,
+				_ => writeln!(stdout, "dependencies declared as used:")?,
+			}
+			print_deps_tree(&self.declared_used_deps, |_| true, &HashMap::new(), &mut stdout)?;
+		}
+		let baselined = self.baselined_deps
+			.values()
+			.map(|deps| deps.normal.len() + deps.development.len() + deps.build.len())
+			.sum::<usize>();
+		if baselined > 0 {
+			writeln!(stdout, "{} finding(s) are accepted by the baseline.", baselined)?;
+		}
+		if !self.fixed_baseline.is_empty() {
+			writeln!(stdout, "fixed since the baseline (remove them from it):")?;
+			print_deps_tree(&self.fixed_baseline, |_| true, &HashMap::new(), &mut stdout)?;
+		}
 		stdout.flush()
 	}
 
@@ -1035,12 +2392,131 @@ impl Outcome {
 	}
 }
 
+/// A direct dependency of a member, see `Outcome::declared_dependencies`.
+struct DeclaredDependency {
+	package_name: InternedString,
+	status: DependencyStatus,
+}
+
+/// The status of a direct dependency in the outcome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DependencyStatus {
+	Used,
+	/// Marked as used with `#[cfg(cargo_udeps)]`
+	DeclaredUsed,
+	Unused,
+	/// Unused, but accepted by `--baseline`
+	Baselined,
+	/// Unused, with the `allow` severity
+	Allowed,
+	Ignored,
+	/// Not passed to any compilation, for example because it is for another platform
+	NotEvaluated,
+}
+
+impl DependencyStatus {
+	fn name(self) -> &'static str {
+		match self {
+			DependencyStatus::Used => "used",
+			DependencyStatus::DeclaredUsed => "declared used",
+			DependencyStatus::Unused => "unused",
+			DependencyStatus::Baselined => "baselined",
+			DependencyStatus::Allowed => "allowed",
+			DependencyStatus::Ignored => "ignored",
+			DependencyStatus::NotEvaluated => "not evaluated",
+		}
+	}
+}
+
+/// Prints the dependencies of the kinds for which `include` returns true,
+/// those with the largest `savings` first.
+fn print_deps_tree(
+	deps: &BTreeMap<PackageId, OutcomeUnusedDeps>,
+	include: impl Fn(dependency::DepKind) -> bool,
+	savings: &HashMap<(PackageId, dependency::DepKind, InternedString), Savings>,
+	mut stdout: impl Write,
+) -> io::Result<()> {
+	let removed_packages = |member: PackageId, kind: dependency::DepKind, dep: InternedString| {
+		savings.get(&(member, kind, dep)).map(|savings| savings.packages.len())
+	};
+	let mut members = deps
+		.iter()
+		.filter(|(_, deps)| deps.any_kind(&include))
+		.collect::<Vec<_>>();
+	// The sort is stable, so members with the same savings stay sorted by ID
+	members.sort_by_key(|&(&member, deps)| {
+		let largest = [dependency::DepKind::Normal, dependency::DepKind::Development, dependency::DepKind::Build]
+			.into_iter()
+			.filter(|&kind| include(kind))
+			.flat_map(|kind| deps.unused_deps(kind).iter().filter_map(move |&dep| removed_packages(member, kind, dep)))
+			.max();
+		Reverse(largest.unwrap_or(0))
+	});
+	let empty = BTreeSet::new();
+	for (&member, deps) in members {
+		let OutcomeUnusedDeps { normal, development, build, evaluated_in, .. } = deps;
+		let filter = |deps, kind| if include(kind) { deps } else { &empty };
+		let normal = filter(normal, dependency::DepKind::Normal);
+		let development = filter(development, dependency::DepKind::Development);
+		let build = filter(build, dependency::DepKind::Build);
+		fn edge_and_joint(p: bool) -> (char, char) {
+			if p {
+				(' ', '└')
+			} else {
+				('│', '├')
+			}
+		}
+
+		writeln!(stdout, "`{}`", member)?;
+
+		for (deps, (edge, joint), prefix, kind) in [
+			(normal, edge_and_joint(development.is_empty() && build.is_empty()), "", dependency::DepKind::Normal),
+			(development, edge_and_joint(build.is_empty()), "dev-", dependency::DepKind::Development),
+			(build, (' ', '└'), "build-", dependency::DepKind::Build),
+		] {
+			if !deps.is_empty() {
+				writeln!(stdout, "{}─── {}dependencies", joint, prefix)?;
+				let mut deps = deps.iter().collect::<Vec<_>>();
+				deps.sort_by_key(|&&dep| Reverse(removed_packages(member, kind, dep).unwrap_or(0)));
+				let mut deps = deps.into_iter().peekable();
+				while let Some(dep) = deps.next() {
+					let joint = if deps.peek().is_some() {
+						'├'
+					} else {
+						'└'
+					};
+					write!(stdout, "{}    {}─── {:?}", edge, joint, dep)?;
+					if let Some(configs) = evaluated_in.get(kind_name(kind)).and_then(|deps| deps.get(dep)) {
+						let configs = configs.iter().map(String::as_str).collect::<Vec<_>>();
+						write!(stdout, " (in {})", configs.join(", "))?;
+					}
+					if let Some(savings) = savings.get(&(member, kind, *dep)) {
+						match savings.packages.len() {
+							1 => write!(stdout, " (removes 1 package")?,
+							count => write!(stdout, " (removes {} packages", count)?,
+						}
+						if let Some(compile_time) = savings.compile_time {
+							write!(stdout, ", {:.2}s of compile time", compile_time.as_secs_f64())?;
+						}
+						write!(stdout, ")")?;
+					}
+					writeln!(stdout)?;
+				}
+			}
+		}
+	}
+	Ok(())
+}
+
 #[derive(Debug, Serialize)]
 struct OutcomeUnusedDeps {
 	manifest_path: String,
 	normal: BTreeSet<InternedString>,
 	development: BTreeSet<InternedString>,
 	build: BTreeSet<InternedString>,
+	/// The configurations each dependency was evaluated in, if `--config-set` was used.
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	evaluated_in: BTreeMap<&'static str, BTreeMap<InternedString, BTreeSet<String>>>,
 }
 
 impl OutcomeUnusedDeps {
@@ -1055,9 +2531,29 @@ impl OutcomeUnusedDeps {
 			normal: BTreeSet::new(),
 			development: BTreeSet::new(),
 			build: BTreeSet::new(),
+			evaluated_in: BTreeMap::new(),
 		})
 	}
 
+	/// Whether there are dependencies of a kind for which `f` returns true.
+	fn any_kind(&self, f: impl Fn(dependency::DepKind) -> bool) -> bool {
+		[
+			(&self.normal, dependency::DepKind::Normal),
+			(&self.development, dependency::DepKind::Development),
+			(&self.build, dependency::DepKind::Build),
+		]
+			.iter()
+			.any(|(deps, kind)| !deps.is_empty() && f(*kind))
+	}
+
+	fn unused_deps(&self, kind: dependency::DepKind) -> &BTreeSet<InternedString> {
+		match kind {
+			dependency::DepKind::Normal => &self.normal,
+			dependency::DepKind::Development => &self.development,
+			dependency::DepKind::Build => &self.build,
+		}
+	}
+
 	fn unused_deps_mut(&mut self, kind: dependency::DepKind) -> &mut BTreeSet<InternedString> {
 		match kind {
 			dependency::DepKind::Normal => &mut self.normal,
@@ -1067,10 +2563,100 @@ impl OutcomeUnusedDeps {
 	}
 }
 
-#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+/// Unused dependencies that were ignored, with the reasons given for ignoring them.
+#[derive(Debug, Default, Serialize)]
+struct OutcomeIgnoredDeps {
+	normal: BTreeMap<InternedString, Option<String>>,
+	development: BTreeMap<InternedString, Option<String>>,
+	build: BTreeMap<InternedString, Option<String>>,
+}
+
+impl OutcomeIgnoredDeps {
+	fn ignored_deps(&self, kind: dependency::DepKind) -> &BTreeMap<InternedString, Option<String>> {
+		match kind {
+			dependency::DepKind::Normal => &self.normal,
+			dependency::DepKind::Development => &self.development,
+			dependency::DepKind::Build => &self.build,
+		}
+	}
+
+	fn ignored_deps_mut(&mut self, kind: dependency::DepKind) -> &mut BTreeMap<InternedString, Option<String>> {
+		match kind {
+			dependency::DepKind::Normal => &mut self.normal,
+			dependency::DepKind::Development => &mut self.development,
+			dependency::DepKind::Build => &mut self.build,
+		}
+	}
+}
+
+/// The notes shown with the results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum NoteCode {
+	/// Not all targets were checked
+	OtherTargets,
+	/// No target selection flags were given, so `--all-targets` can be suggested
+	EnableAllTargets,
+	/// Some dependencies are not libraries
+	NonLibPackages,
+	FalsePositives,
+}
+
+impl NoteCode {
+	/// The text in the human output and the `note` field of the version 1 JSON.
+	fn human(self) -> &'static str {
+		match self {
+			NoteCode::OtherTargets => "Note: These dependencies might be used by other targets.\n",
+			NoteCode::EnableAllTargets => "      To find dependencies that are not used by any target, enable `--all-targets`.\n",
+			NoteCode::NonLibPackages => concat!(
+				"Note: Some dependencies are non-library packages.\n",
+				"      `cargo-udeps` regards them as unused.\n",
+			),
+			NoteCode::FalsePositives => concat!(
+				"Note: They might be false-positive.\n",
+				"      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.\n",
+				"      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.\n",
+			),
+		}
+	}
+
+	fn message(self) -> &'static str {
+		match self {
+			NoteCode::OtherTargets => "These dependencies might be used by other targets.",
+			NoteCode::EnableAllTargets => "To find dependencies that are not used by any target, enable `--all-targets`.",
+			NoteCode::NonLibPackages => "Some dependencies are non-library packages. `cargo-udeps` regards them as unused.",
+			NoteCode::FalsePositives => concat!(
+				"They might be false-positive. ",
+				"For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests. ",
+				"To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.",
+			),
+		}
+	}
+}
+
+/// The name of a dependency kind as used in `package.metadata.cargo-udeps.ignore`.
+fn kind_name(kind: dependency::DepKind) -> &'static str {
+	match kind {
+		dependency::DepKind::Normal => "normal",
+		dependency::DepKind::Development => "development",
+		dependency::DepKind::Build => "build",
+	}
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
 enum OutputKind {
+	#[default]
 	Human,
 	Json,
+	JsonV2,
+	Sarif,
+	Junit,
+	Diagnostic,
+	RustcJson,
+	Markdown,
+	Dot,
+	Html,
 }
 
 impl FromStr for OutputKind {
@@ -1080,14 +2666,29 @@ impl FromStr for OutputKind {
 		match s {
 			"human" => Ok(Self::Human),
 			"json" => Ok(Self::Json),
-			_ => Err(r#"expected "human" or "json" (you should not see this message)"#),
+			"json-v2" => Ok(Self::JsonV2),
+			"sarif" => Ok(Self::Sarif),
+			"junit" => Ok(Self::Junit),
+			"diagnostic" => Ok(Self::Diagnostic),
+			"rustc-json" => Ok(Self::RustcJson),
+			"markdown" => Ok(Self::Markdown),
+			"dot" => Ok(Self::Dot),
+			"html" => Ok(Self::Html),
+			_ => Err(r#"expected "human", "json", "json-v2", "sarif", "junit", "diagnostic", "rustc-json", "markdown", "dot" or "html" (you should not see this message)"#),
 		}
 	}
 }
 
-#[derive(clap::ValueEnum, Clone, Copy, Debug)]
-enum Backend {
+/// How used dependencies are determined.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Backend {
+	/// From the dependency info files that rustc writes
+	#[default]
 	Depinfo,
+	/// From a program given with `--backend-command`, see the README for the protocol
+	External,
 }
 
 impl FromStr for Backend {
@@ -1096,7 +2697,73 @@ impl FromStr for Backend {
 	fn from_str(s: &str) -> std::result::Result<Self, &'static str> {
 		match s {
 			"depinfo" => Ok(Self::Depinfo),
-			_ => Err(r#"expected "depinfo" (you should not see this message)"#),
+			"external" => Ok(Self::External),
+			_ => Err(r#"expected "depinfo" or "external" (you should not see this message)"#),
+		}
+	}
+}
+
+/// How a finding is reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+	/// Not reported
+	Allow,
+	/// Reported, but doesn't fail the run
+	Warn,
+	/// Reported and fails the run
+	Deny,
+}
+
+impl FromStr for Severity {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, String> {
+		match s {
+			"allow" => Ok(Self::Allow),
+			"warn" => Ok(Self::Warn),
+			"deny" => Ok(Self::Deny),
+			_ => Err(format!(r#"expected "allow", "warn" or "deny", found {:?}"#, s)),
+		}
+	}
+}
+
+/// Parses a `--severity CATEGORY=LEVEL` argument.
+fn parse_severity(s: &str) -> std::result::Result<(String, Severity), String> {
+	let (category, severity) = s
+		.split_once('=')
+		.ok_or_else(|| format!("expected `CATEGORY=LEVEL`, found {:?}", s))?;
+	if !SeverityPolicy::CATEGORIES.contains(&category) {
+		return Err(format!(
+			"unknown category {:?}, expected one of {}",
+			category,
+			SeverityPolicy::CATEGORIES.join(", "),
+		));
+	}
+	Ok((category.to_owned(), severity.parse()?))
+}
+
+/// The severity of each finding category, after merging the command line with the configuration.
+#[derive(Clone, Debug, Serialize)]
+struct Severities {
+	normal: Severity,
+	development: Severity,
+	build: Severity,
+	declared_used: Severity,
+}
+
+impl Default for Severities {
+	fn default() -> Self {
+		SeverityPolicy::default().resolve()
+	}
+}
+
+impl Severities {
+	fn unused(&self, kind: dependency::DepKind) -> Severity {
+		match kind {
+			dependency::DepKind::Normal => self.normal,
+			dependency::DepKind::Development => self.development,
+			dependency::DepKind::Build => self.build,
 		}
 	}
 }
@@ -1124,3 +2791,59 @@ impl ShellExt for Shell {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use pretty_assertions::assert_eq;
+
+	use super::{blank_comments_and_literals, declared_used_crates};
+
+	#[test]
+	fn blank_literals() {
+		for (source, expected) in [
+			(r#"a "b\"c" d"#, "a        d"),
+			(r##"a r#"b"c"# d"##, "a          d"),
+			(r#"a br"b" c"#, "a b     c"),
+			(r#"a r"#, "a r"),
+			(r"a b'\'' c", "a b     c"),
+			(r"a '\\' c", "a      c"),
+			("a 'é' c", "a      c"),
+			(r#"a '"' b "'" c"#, "a     b     c"),
+			(r"fn f<'a>(x :&'a u8, y :&'b u8) {}", r"fn f<'a>(x :&'a u8, y :&'b u8) {}"),
+		] {
+			assert_eq!(expected, blank_comments_and_literals(source), "{}", source);
+		}
+	}
+
+	#[test]
+	fn blank_comments() {
+		for (source, expected) in [
+			("a // b \"\nc", "a       \nc"),
+			("a /* b /* c */ d */ e", "a                   e"),
+			("a /* b\n */ c", "a     \n    c"),
+			("a \"/*\" b */", "a      b */"),
+			("a /* \" */ b", "a         b"),
+		] {
+			assert_eq!(expected, blank_comments_and_literals(source), "{}", source);
+		}
+	}
+
+	#[test]
+	fn markers() {
+		static SOURCE :&str = r##"
+#[cfg(cargo_udeps)]
+use a as _;
+#[cfg( cargo_udeps )] use ::b::{self as _};
+#[cfg(all(cargo_udeps))]
+#[allow(unused_imports)]
+pub use c as _;
+#[cfg(cargo_udeps)] pub(crate) extern crate d;
+#[cfg(not(cargo_udeps))] use e as _;
+#[cfg(test)] use f as _;
+#[cfg(cargo_udeps)] fn g() { use h as _; }
+// #[cfg(cargo_udeps)] use i as _;
+const J :&str = "#[cfg(cargo_udeps)] use j as _;";
+"##;
+		assert_eq!(vec!["a", "b", "c", "d"], declared_used_crates(SOURCE));
+	}
+}
@@ -0,0 +1,312 @@
+//! `cargo udeps lsp`: a language server that publishes the findings as diagnostics on `Cargo.toml`.
+//!
+//! It speaks just enough of the Language Server Protocol over stdin and stdout:
+//! diagnostics for open manifests, a new analysis when a file is saved, and a
+//! quick fix that removes an unused dependency like the `--output rustc-json` suggestion does.
+//! Like `--watch`, only the members whose sources changed are analyzed again,
+//! and none if no file the last analysis depended on changed.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::time::SystemTime;
+
+use anyhow::Context;
+use cargo::core::shell::Shell;
+use cargo::util::context::GlobalContext;
+use cargo::CargoResult;
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::api::cli_error;
+use crate::diagnostic::{self, Finding};
+use crate::manifest::Manifest;
+use crate::watch::{self, Snapshot, WatchedFiles};
+use crate::{AnalysisOptions, Incremental, Outcome, ShellExt};
+
+const METHOD_NOT_FOUND :i64 = -32601;
+
+const SEVERITY_ERROR :u8 = 1;
+const SEVERITY_WARNING :u8 = 2;
+const SEVERITY_INFORMATION :u8 = 3;
+
+const MESSAGE_TYPE_ERROR :u8 = 1;
+
+pub(crate) fn serve(
+	options :&AnalysisOptions,
+	config :&mut GlobalContext,
+	mut stdin :impl BufRead,
+	stdout :impl Write,
+) -> CargoResult<()> {
+	// stdout belongs to the protocol, so everything that cargo prints goes to stderr
+	*config.shell() = Shell::from_write(Box::new(io::stderr()));
+
+	let mut server = Server {
+		options,
+		config,
+		stdout,
+		documents : HashMap::new(),
+		outcome : None,
+		incremental : Incremental::default(),
+		files : None,
+	};
+	while let Some(message) = read_message(&mut stdin)? {
+		let method = message["method"].as_str().unwrap_or("");
+		let params = &message["params"];
+		match (method, message.get("id")) {
+			("exit", _) => break,
+			("initialize", Some(id)) => server.respond(id, json!({
+				"capabilities": {
+					"textDocumentSync": { "openClose": true, "change": 1, "save": true },
+					"codeActionProvider": { "codeActionKinds": ["quickfix"] },
+				},
+				"serverInfo": { "name": "cargo-udeps", "version": env!("CARGO_PKG_VERSION") },
+			}))?,
+			("shutdown", Some(id)) => server.respond(id, Value::Null)?,
+			("textDocument/codeAction", Some(id)) => {
+				let actions = server.code_actions(params);
+				server.respond(id, Value::Array(actions))?;
+			},
+			(_, Some(id)) => server.send(&json!({
+				"jsonrpc": "2.0",
+				"id": id,
+				"error": { "code": METHOD_NOT_FOUND, "message": format!("unsupported method `{}`", method) },
+			}))?,
+			("textDocument/didOpen", None) => {
+				let Some(uri) = document_uri(params) else { continue };
+				let text = params["textDocument"]["text"].as_str().unwrap_or("").to_owned();
+				server.documents.insert(uri.clone(), text);
+				if is_manifest(&uri) {
+					server.refresh()?;
+					server.publish(&uri)?;
+				}
+			},
+			("textDocument/didChange", None) => {
+				let Some(uri) = document_uri(params) else { continue };
+				// Full document sync: the last change has the whole text
+				if let Some(text) = params["contentChanges"].as_array().and_then(|changes| changes.last()) {
+					let text = text["text"].as_str().unwrap_or("").to_owned();
+					server.documents.insert(uri, text);
+				}
+			},
+			("textDocument/didSave", None) => {
+				server.refresh()?;
+				let manifests = server.documents.keys().filter(|uri| is_manifest(uri)).cloned().collect::<Vec<_>>();
+				for uri in manifests {
+					server.publish(&uri)?;
+				}
+			},
+			("textDocument/didClose", None) => {
+				let Some(uri) = document_uri(params) else { continue };
+				server.documents.remove(&uri);
+				if is_manifest(&uri) {
+					server.send_diagnostics(&uri, Vec::new())?;
+				}
+			},
+			_ => {},
+		}
+	}
+	Ok(())
+}
+
+struct Server<'a, W> {
+	options :&'a AnalysisOptions,
+	config :&'a mut GlobalContext,
+	stdout :W,
+	/// The text of the open documents
+	documents :HashMap<Url, String>,
+	/// The last analysis, `None` if it failed
+	outcome :Option<Outcome>,
+	/// What is kept from one analysis to the next
+	incremental :Incremental,
+	/// The files of the last successful analysis, as it saw them
+	files :Option<(WatchedFiles, Snapshot)>,
+}
+
+impl<W :Write> Server<'_, W> {
+	/// Analyzes the members affected by the changed files again, unless nothing changed.
+	fn refresh(&mut self) -> CargoResult<()> {
+		// Taken before the analysis, so that files saved during it cause another one
+		let started = SystemTime::now();
+		let before = match &self.files {
+			Some((files, seen)) => {
+				let before = files.snapshot();
+				let changed = watch::changes(seen, before.clone());
+				if changed.is_empty() && self.outcome.is_some() {
+					return self.config.shell().info("Nothing changed since the last analysis");
+				}
+				if !self.incremental.analyses.is_empty() {
+					self.incremental.changed = files.affected_members(&changed);
+				}
+				before
+			},
+			None => Snapshot::new(),
+		};
+		self.outcome = None;
+		match self.options.outcome_incremental(self.config, &mut self.incremental, None) {
+			Ok(outcome) => {
+				let files = WatchedFiles::new(&outcome);
+				let seen = files.snapshot_seen(&before, started);
+				self.files = Some((files, seen));
+				self.outcome = Some(outcome);
+			},
+			Err(err) => {
+				// The analyses may be outdated, or there may be none for some members
+				self.incremental.analyses.clear();
+				let err = cli_error(err);
+				self.config.shell().error(&err)?;
+				self.send(&json!({
+					"jsonrpc": "2.0",
+					"method": "window/showMessage",
+					"params": { "type": MESSAGE_TYPE_ERROR, "message": format!("cargo-udeps: {:#}", err) },
+				}))?;
+			},
+		}
+		Ok(())
+	}
+
+	/// Publishes the diagnostics of the manifest `uri`, none if the last analysis failed.
+	fn publish(&mut self, uri :&Url) -> CargoResult<()> {
+		let diagnostics = match &self.outcome {
+			Some(outcome) => findings_in(outcome, uri)
+				.iter()
+				.map(to_diagnostic)
+				.collect(),
+			None => Vec::new(),
+		};
+		self.send_diagnostics(uri, diagnostics)
+	}
+
+	fn send_diagnostics(&mut self, uri :&Url, diagnostics :Vec<Value>) -> CargoResult<()> {
+		self.send(&json!({
+			"jsonrpc": "2.0",
+			"method": "textDocument/publishDiagnostics",
+			"params": { "uri": uri, "diagnostics": diagnostics },
+		}))
+	}
+
+	/// The quick fixes for the unused dependencies in the requested range.
+	fn code_actions(&self, params :&Value) -> Vec<Value> {
+		let (Some(uri), Some(outcome)) = (document_uri(params), &self.outcome) else {
+			return Vec::new();
+		};
+		let (first_line, last_line) = (&params["range"]["start"]["line"], &params["range"]["end"]["line"]);
+		let (Some(first_line), Some(last_line)) = (first_line.as_u64(), last_line.as_u64()) else {
+			return Vec::new();
+		};
+		findings_in(outcome, &uri)
+			.iter()
+			.filter(|finding| finding.unused)
+			.filter_map(|finding| {
+				let manifest = finding.manifest?;
+				let removal = finding.entry?.removal.as_ref()?;
+				// The offsets are only valid for the text that was analyzed
+				if self.documents.get(&uri).is_some_and(|text| text != manifest.source()) {
+					return None;
+				}
+				let diagnostic = to_diagnostic(finding);
+				let range = &diagnostic["range"];
+				let (start, end) = (range["start"]["line"].as_u64()?, range["end"]["line"].as_u64()?);
+				if end < first_line || start > last_line {
+					return None;
+				}
+				Some(json!({
+					"title": format!("Remove {}", finding.message),
+					"kind": "quickfix",
+					"diagnostics": [diagnostic],
+					"isPreferred": removal.exact,
+					"edit": {
+						"changes": {
+							uri.as_str(): [{ "range": range_of(manifest, removal.span.clone()), "newText": "" }],
+						},
+					},
+				}))
+			})
+			.collect()
+	}
+
+	fn respond(&mut self, id :&Value, result :Value) -> CargoResult<()> {
+		self.send(&json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+	}
+
+	fn send(&mut self, message :&Value) -> CargoResult<()> {
+		let body = message.to_string();
+		write!(self.stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+		self.stdout.flush()?;
+		Ok(())
+	}
+}
+
+/// Reads a message, or returns `None` at the end of the input.
+fn read_message(stdin :&mut impl BufRead) -> CargoResult<Option<Value>> {
+	let mut length = None;
+	loop {
+		let mut header = String::new();
+		if stdin.read_line(&mut header)? == 0 {
+			return Ok(None);
+		}
+		let header = header.trim_end();
+		if header.is_empty() {
+			break;
+		}
+		if let Some(value) = header.strip_prefix("Content-Length:") {
+			length = Some(value.trim().parse::<usize>().context("invalid `Content-Length` header")?);
+		}
+	}
+	let mut body = vec![0; length.context("missing `Content-Length` header")?];
+	stdin.read_exact(&mut body)?;
+	Ok(Some(serde_json::from_slice(&body).context("could not parse a message")?))
+}
+
+fn document_uri(params :&Value) -> Option<Url> {
+	params["textDocument"]["uri"].as_str()?.parse().ok()
+}
+
+fn is_manifest(uri :&Url) -> bool {
+	uri.path().ends_with("/Cargo.toml")
+}
+
+fn findings_in<'a>(outcome :&'a Outcome, uri :&Url) -> Vec<Finding<'a>> {
+	let Ok(path) = uri.to_file_path() else {
+		return Vec::new();
+	};
+	diagnostic::findings(outcome)
+		.into_iter()
+		.filter(|finding| outcome.workspace_root.join(finding.manifest_path) == path)
+		.collect()
+}
+
+fn to_diagnostic(finding :&Finding<'_>) -> Value {
+	let range = finding.manifest
+		.zip(finding.entry.and_then(|entry| entry.span.clone()))
+		.map(|(manifest, span)| range_of(manifest, span))
+		.unwrap_or_else(|| json!({
+			"start": { "line": 0, "character": 0 },
+			"end": { "line": 0, "character": 0 },
+		}));
+	let severity = match finding.level {
+		"error" => SEVERITY_ERROR,
+		"warning" => SEVERITY_WARNING,
+		_ => SEVERITY_INFORMATION,
+	};
+	json!({
+		"range": range,
+		"severity": severity,
+		"source": "cargo-udeps",
+		"message": finding.message,
+	})
+}
+
+fn range_of(manifest :&Manifest, span :std::ops::Range<usize>) -> Value {
+	json!({ "start": position(manifest, span.start), "end": position(manifest, span.end) })
+}
+
+/// A position in the protocol's terms: a 0-based line and a column in UTF-16 code units.
+fn position(manifest :&Manifest, offset :usize) -> Value {
+	let (line, column) = manifest.line_column(offset);
+	let character = manifest.line(line)
+		.chars()
+		.take(column - 1)
+		.map(char::len_utf16)
+		.sum::<usize>();
+	json!({ "line": line - 1, "character": character })
+}
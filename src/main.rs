@@ -1,4 +1,4 @@
-use std::{env, io};
+use std::{env, io, process};
 
 use cargo::core::shell::Shell;
 
@@ -6,6 +6,11 @@ fn main() {
 	let mut config = cargo::util::context::GlobalContext::default()
 		.unwrap_or_else(|e| cargo::exit_with_error(e.into(), &mut Shell::new()));
 	if let Err(err) = cargo_udeps::run(env::args_os(), &mut config, io::stdout()) {
+		// `exit_with_error` would exit with 1 for invalid arguments, which means unused dependencies
+		if let Some(clap_err) = err.error.as_ref().and_then(|err| err.downcast_ref::<clap::Error>()) {
+			let _ = clap_err.print();
+			process::exit(err.exit_code);
+		}
 		cargo::exit_with_error(err, &mut config.shell());
 	}
 }
@@ -0,0 +1,204 @@
+//! Span-preserving reading of `Cargo.toml` files.
+//!
+//! Cargo's own manifest types don't keep comments or positions,
+//! so the dependency tables are read again with `toml_edit`.
+
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use anyhow::Context;
+use cargo::core::dependency::DepKind;
+use cargo::CargoResult;
+use toml_edit::{Document, Item, Key, RawString, Table};
+
+/// A dependency entry of a manifest.
+#[derive(Clone, Debug)]
+pub(crate) struct ManifestDependency {
+	pub(crate) kind :DepKind,
+	pub(crate) name_in_toml :String,
+	/// The byte range of the entry's key in the source
+	pub(crate) span :Option<Range<usize>>,
+	/// Whether the entry is a table of its own, like `[dependencies.foo]`
+	pub(crate) is_table :bool,
+	/// Whether the entry is inherited from the workspace with `workspace = true`
+	pub(crate) inherited :bool,
+	/// The part of the source to remove to remove the entry
+	pub(crate) removal :Option<Removal>,
+	/// A `# udeps: ignore` or `# udeps: ignore(reason)` comment
+	/// on the line of the entry or directly before it.
+	pub(crate) ignore :Option<IgnoreAnnotation>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Removal {
+	pub(crate) span :Range<usize>,
+	/// Whether the span only contains the entry, so that removing it is safe
+	pub(crate) exact :bool,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct IgnoreAnnotation {
+	pub(crate) reason :Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Manifest {
+	source :String,
+	pub(crate) dependencies :Vec<ManifestDependency>,
+}
+
+const DEPENDENCY_TABLES :&[(&str, DepKind)] = &[
+	("dependencies", DepKind::Normal),
+	("dev-dependencies", DepKind::Development),
+	("dev_dependencies", DepKind::Development),
+	("build-dependencies", DepKind::Build),
+	("build_dependencies", DepKind::Build),
+];
+
+impl Manifest {
+	pub(crate) fn load(path :&Path) -> CargoResult<Self> {
+		let source = fs::read_to_string(path)
+			.with_context(|| format!("could not read `{}`", path.display()))?;
+		let dependencies = Self::parse(&source)
+			.with_context(|| format!("could not parse `{}`", path.display()))?;
+		Ok(Self { source, dependencies })
+	}
+
+	/// The entry of the dependency `name_in_toml` of the given kind.
+	///
+	/// If there are several, for example for different platforms, the first one is returned.
+	pub(crate) fn dependency(&self, kind :DepKind, name_in_toml :&str) -> Option<&ManifestDependency> {
+		self.dependencies
+			.iter()
+			.find(|dep| dep.kind == kind && dep.name_in_toml == name_in_toml)
+	}
+
+	pub(crate) fn source(&self) -> &str {
+		&self.source
+	}
+
+	/// The 1-based line and column (in characters) of a byte offset.
+	pub(crate) fn line_column(&self, offset :usize) -> (usize, usize) {
+		let before = &self.source[..offset];
+		let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+		(before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+	}
+
+	/// The text of a 1-based line, without the line ending.
+	pub(crate) fn line(&self, line :usize) -> &str {
+		self.source.lines().nth(line - 1).unwrap_or("")
+	}
+
+	fn parse(source :&str) -> CargoResult<Vec<ManifestDependency>> {
+		let doc = Document::parse(source)?;
+		let mut dependencies = Vec::new();
+		let mut read_tables = |table :&Table| {
+			for &(table_name, kind) in DEPENDENCY_TABLES {
+				if let Some(deps) = table.get(table_name).and_then(Item::as_table_like) {
+					for (name, item) in deps.iter() {
+						let Some((key, _)) = deps.get_key_value(name) else {
+							continue;
+						};
+						let mut comments = vec![raw(source, key.leaf_decor().prefix())];
+						comments.extend(item_comments(source, item));
+						dependencies.push(ManifestDependency {
+							kind,
+							name_in_toml : name.to_owned(),
+							span : key.span(),
+							is_table : item.is_table(),
+							inherited : item.get("workspace").and_then(Item::as_bool) == Some(true),
+							removal : removal(source, key, item),
+							ignore : comments.into_iter().find_map(parse_ignore_annotation),
+						});
+					}
+				}
+			}
+		};
+		read_tables(doc.as_table());
+		if let Some(targets) = doc.as_table().get("target").and_then(Item::as_table) {
+			for (_, item) in targets.iter() {
+				if let Some(table) = item.as_table() {
+					read_tables(table);
+				}
+			}
+		}
+		Ok(dependencies)
+	}
+}
+
+/// The text of a part of the decor of a parsed document.
+fn raw<'a>(source :&'a str, raw :Option<&'a RawString>) -> &'a str {
+	raw.and_then(|raw| raw.as_str().or_else(|| source.get(raw.span()?)))
+		.unwrap_or("")
+}
+
+/// The lines of an entry.
+///
+/// Tables like `[dependencies.foo]` extend to the next header, which is only a guess,
+/// and entries with dotted keys like `foo.version = "1.0"` aren't supported.
+fn removal(source :&str, key :&Key, item :&Item) -> Option<Removal> {
+	let line_start = |offset :usize| source[..offset].rfind('\n').map_or(0, |i| i + 1);
+	let line_end = |offset :usize| source[offset..].find('\n').map_or(source.len(), |i| offset + i + 1);
+	match item {
+		Item::Value(value) => {
+			let (key, value) = (key.span()?, value.span()?);
+			let span = line_start(key.start)..line_end(value.end);
+			let before = source[span.start..key.start].trim();
+			let after = source[value.end..span.end].trim();
+			let exact = before.is_empty() && (after.is_empty() || after.starts_with('#'));
+			Some(Removal { span, exact })
+		},
+		Item::Table(table) if !table.is_dotted() => {
+			let header = table.span()?;
+			let mut end = line_end(header.end);
+			while end < source.len() && !source[end..].trim_start().starts_with('[') {
+				end = line_end(end);
+			}
+			Some(Removal { span : line_start(header.start)..end, exact : false })
+		},
+		_ => None,
+	}
+}
+
+/// The comments directly before and after the header or value of an entry.
+fn item_comments<'a>(source :&'a str, item :&'a Item) -> Vec<&'a str> {
+	match item {
+		Item::Value(value) => vec![raw(source, value.decor().suffix())],
+		Item::Table(table) if table.is_dotted() => table
+			.iter()
+			.filter_map(|(name, item)| {
+				let (key, _) = table.get_key_value(name)?;
+				let mut comments = vec![raw(source, key.leaf_decor().prefix())];
+				comments.extend(item_comments(source, item));
+				Some(comments)
+			})
+			.flatten()
+			.collect(),
+		Item::Table(table) => vec![
+			raw(source, table.decor().prefix()),
+			raw(source, table.decor().suffix()),
+		],
+		Item::None | Item::ArrayOfTables(_) => Vec::new(),
+	}
+}
+
+fn parse_ignore_annotation(comments :&str) -> Option<IgnoreAnnotation> {
+	comments.lines().find_map(|line| {
+		let annotation = line
+			.trim()
+			.strip_prefix('#')?
+			.trim()
+			.strip_prefix("udeps:")?
+			.trim()
+			.strip_prefix("ignore")?
+			.trim();
+		let reason = if annotation.is_empty() {
+			None
+		} else {
+			let reason = annotation.strip_prefix('(')?.strip_suffix(')')?.trim();
+			Some(reason.to_owned()).filter(|reason| !reason.is_empty())
+		};
+		Some(IgnoreAnnotation { reason })
+	})
+}
@@ -0,0 +1,133 @@
+//! `--output markdown`: a report for pull request comments.
+//!
+//! The output only depends on the findings, so that repeated runs produce identical comments.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+
+use cargo::core::dependency::DepKind;
+
+use crate::diagnostic::help;
+use crate::{Outcome, Severity};
+
+const KINDS :&[(DepKind, &str)] = &[
+	(DepKind::Normal, "dependencies"),
+	(DepKind::Development, "dev-dependencies"),
+	(DepKind::Build, "build-dependencies"),
+];
+
+pub(crate) fn print(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	let mut md = String::new();
+	let count = |severity :Severity| outcome.unused_deps
+		.values()
+		.flat_map(|deps| KINDS.iter().map(move |&(kind, _)| (deps, kind)))
+		.filter(|&(_, kind)| outcome.severity.unused(kind) == severity)
+		.map(|(deps, kind)| deps.unused_deps(kind).len())
+		.sum::<usize>();
+	let (denied, warned) = (count(Severity::Deny), count(Severity::Warn));
+	let ignored = outcome.ignored_deps
+		.values()
+		.flat_map(|deps| KINDS.iter().map(move |&(kind, _)| deps.ignored_deps(kind).len()))
+		.sum::<usize>();
+
+	let mut summary = Vec::new();
+	if denied > 0 {
+		summary.push(plural(denied, "unused dependency", "unused dependencies"));
+	}
+	if warned > 0 {
+		summary.push(plural(warned, "warning", "warnings"));
+	}
+	if ignored > 0 {
+		summary.push(format!("{} ignored", ignored));
+	}
+	if denied + warned == 0 {
+		summary.insert(0, "all dependencies seem to be used".to_owned());
+	}
+	writeln!(md, "**cargo-udeps**: {}", summary.join(", ")).unwrap();
+
+	for (id, deps) in &outcome.unused_deps {
+		let manifest_path = Path::new(&deps.manifest_path);
+		let manifest_path = manifest_path.strip_prefix(&outcome.workspace_root).unwrap_or(manifest_path);
+		let manifest_path = manifest_path.to_string_lossy().replace('\\', "/");
+		let manifest = outcome.manifests.get(id);
+
+		writeln!(md).unwrap();
+		writeln!(md, "#### `{} v{}`", id.name(), id.version()).unwrap();
+		writeln!(md).unwrap();
+		writeln!(md, "| Dependency | Kind | Level | Manifest | Action |").unwrap();
+		writeln!(md, "|---|---|---|---|---|").unwrap();
+		for &(kind, table) in KINDS {
+			let level = match outcome.severity.unused(kind) {
+				Severity::Deny => "error",
+				Severity::Warn => "warning",
+				Severity::Allow => continue,
+			};
+			for dep in deps.unused_deps(kind) {
+				let entry = manifest.and_then(|manifest| Some((manifest, manifest.dependency(kind, dep)?)));
+				let (link, action) = match entry {
+					Some((manifest, entry)) => {
+						let link = match &entry.span {
+							Some(span) => {
+								let (line, _) = manifest.line_column(span.start);
+								format!("[{0}:{1}]({0}#L{1})", manifest_path, line)
+							},
+							None => format!("[{0}]({0})", manifest_path),
+						};
+						let mut action = help(entry);
+						if entry.inherited {
+							action += ", and from `[workspace.dependencies]` if no other member uses it";
+						}
+						(link, action)
+					},
+					None => (format!("[{0}]({0})", manifest_path), "remove the dependency".to_owned()),
+				};
+				writeln!(md, "| `{}` | {} | {} | {} | {} |", dep, table, level, link, escape(&action)).unwrap();
+			}
+		}
+	}
+
+	let notes = outcome.notes.iter().map(|note| note.message()).collect::<Vec<_>>();
+	if !notes.is_empty() {
+		writeln!(md).unwrap();
+		writeln!(md, "<details>").unwrap();
+		writeln!(md, "<summary>Notes</summary>").unwrap();
+		writeln!(md).unwrap();
+		for note in notes {
+			writeln!(md, "- {}", note).unwrap();
+		}
+		writeln!(md).unwrap();
+		writeln!(md, "</details>").unwrap();
+	}
+
+	if ignored > 0 {
+		writeln!(md).unwrap();
+		writeln!(md, "<details>").unwrap();
+		writeln!(md, "<summary>Ignored dependencies ({})</summary>", ignored).unwrap();
+		writeln!(md).unwrap();
+		writeln!(md, "| Package | Dependency | Kind | Reason |").unwrap();
+		writeln!(md, "|---|---|---|---|").unwrap();
+		for (id, deps) in &outcome.ignored_deps {
+			for &(kind, table) in KINDS {
+				for (dep, reason) in deps.ignored_deps(kind) {
+					let reason = reason.as_deref().map(escape).unwrap_or_default();
+					writeln!(md, "| `{}` | `{}` | {} | {} |", id.name(), dep, table, reason).unwrap();
+				}
+			}
+		}
+		writeln!(md).unwrap();
+		writeln!(md, "</details>").unwrap();
+	}
+
+	stdout.write_all(md.as_bytes())?;
+	stdout.flush()
+}
+
+fn plural(count :usize, singular :&str, plural :&str) -> String {
+	format!("{} {}", count, if count == 1 { singular } else { plural })
+}
+
+/// Escapes text for a table cell.
+fn escape(text :&str) -> String {
+	text.replace('|', "\\|").replace('\n', " ")
+}
@@ -0,0 +1,286 @@
+//! `--output json-v2`: the versioned JSON report.
+//!
+//! The schema is documented in the README. Within a version, fields are only ever added.
+//! `--output json` keeps printing the unversioned format of older versions.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use cargo::core::dependency::DepKind;
+use cargo::core::package_id::PackageId;
+use cargo::util::interning::InternedString;
+use serde::Serialize;
+
+use crate::{kind_name, EffectiveOptions, NoteCode, Outcome, Severities, Severity};
+
+const VERSION :u32 = 2;
+
+#[derive(Serialize)]
+struct Report<'a> {
+	version :u32,
+	cargo_udeps_version :&'static str,
+	/// The first line of `rustc -vV`
+	toolchain :Option<&'a str>,
+	success :bool,
+	options :&'a EffectiveOptions,
+	configs :&'a BTreeMap<String, EffectiveOptions>,
+	severity :&'a Severities,
+	findings :Vec<Finding>,
+	notes :Vec<Note>,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Category {
+	Unused,
+	DeclaredUsed,
+	Ignored,
+	Baselined,
+	FixedBaseline,
+}
+
+#[derive(Serialize)]
+struct Finding {
+	category :Category,
+	/// `null` for ignored, baselined and fixed baseline dependencies
+	severity :Option<Severity>,
+	package :PackageInfo,
+	dependency :DependencyInfo,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	ignore_reason :Option<String>,
+	/// The configurations the dependency was evaluated in, if `--config-set` was used
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	evaluated_in :Vec<String>,
+	/// What removing an unused dependency would save
+	#[serde(skip_serializing_if = "Option::is_none")]
+	savings :Option<SavingsInfo>,
+}
+
+#[derive(Serialize)]
+struct SavingsInfo {
+	/// The number of packages that would leave the resolve
+	removed_packages :usize,
+	/// These packages, as `name@version`
+	packages :Vec<String>,
+	/// With `--compile-times`, how long these packages took to compile in this run
+	#[serde(skip_serializing_if = "Option::is_none")]
+	compile_time_secs :Option<f64>,
+}
+
+#[derive(Serialize)]
+struct PackageInfo {
+	/// A package ID specification, like in cargo's JSON messages
+	id :String,
+	name :String,
+	version :String,
+	source :String,
+	manifest_path :String,
+}
+
+#[derive(Serialize)]
+struct DependencyInfo {
+	name_in_toml :String,
+	/// The name of the package on the registry, which differs from `name_in_toml` for renamed dependencies
+	package_name :Option<String>,
+	kind :&'static str,
+	optional :bool,
+	/// The `target.'cfg(..)'` the dependency is declared for, `null` if it is declared for all targets
+	target :Option<String>,
+	/// The 1-based line and column of the entry in the manifest
+	location :Option<Location>,
+}
+
+#[derive(Serialize)]
+struct Location {
+	line :usize,
+	column :usize,
+}
+
+#[derive(Serialize)]
+struct Note {
+	code :NoteCode,
+	message :&'static str,
+}
+
+/// A message in cargo's `--message-format json` stream.
+#[derive(Serialize)]
+struct Message {
+	reason :&'static str,
+	/// Like in cargo's messages
+	package_id :String,
+	manifest_path :String,
+	#[serde(flatten)]
+	finding :Finding,
+}
+
+/// Prints a message for each unused dependency, for `--message-format json`.
+pub(crate) fn print_messages(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	for finding in findings(outcome) {
+		if !matches!(finding.category, Category::Unused) {
+			continue;
+		}
+		let message = Message {
+			reason : "cargo-udeps-unused-dependency",
+			package_id : finding.package.id.clone(),
+			manifest_path : finding.package.manifest_path.clone(),
+			finding,
+		};
+		let json = serde_json::to_string(&message).expect("should not fail");
+		writeln!(stdout, "{}", json)?;
+	}
+	stdout.flush()
+}
+
+pub(crate) fn print(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	let report = Report {
+		version : VERSION,
+		cargo_udeps_version : env!("CARGO_PKG_VERSION"),
+		toolchain : outcome.toolchain.as_deref().and_then(|toolchain| toolchain.lines().next()),
+		success : outcome.success,
+		options : &outcome.options,
+		configs : &outcome.configs,
+		severity : &outcome.severity,
+		findings : findings(outcome),
+		notes : outcome.notes
+			.iter()
+			.map(|&code| Note { code, message : code.message() })
+			.collect(),
+	};
+	let json = serde_json::to_string(&report).expect("should not fail");
+	writeln!(stdout, "{}", json)?;
+	stdout.flush()
+}
+
+fn findings(outcome :&Outcome) -> Vec<Finding> {
+	let mut findings = Vec::new();
+	for (category, deps_by_member) in [
+		(Category::Unused, &outcome.unused_deps),
+		(Category::DeclaredUsed, &outcome.declared_used_deps),
+	] {
+		for (&id, deps) in deps_by_member {
+			for kind in [DepKind::Normal, DepKind::Development, DepKind::Build] {
+				let severity = match category {
+					Category::DeclaredUsed => outcome.severity.declared_used,
+					_ => outcome.severity.unused(kind),
+				};
+				for dep in deps.unused_deps(kind) {
+					let evaluated_in = deps.evaluated_in
+						.get(kind_name(kind))
+						.and_then(|deps| deps.get(dep))
+						.map(|configs| configs.iter().cloned().collect())
+						.unwrap_or_default();
+					findings.push(Finding {
+						category,
+						severity : Some(severity),
+						package : package_info(outcome, id),
+						dependency : dependency_info(outcome, id, kind, dep),
+						ignore_reason : None,
+						evaluated_in,
+						savings : match category {
+							Category::Unused => savings_info(outcome, id, kind, *dep),
+							_ => None,
+						},
+					});
+				}
+			}
+		}
+	}
+	for (category, deps_by_member) in [
+		(Category::Baselined, &outcome.baselined_deps),
+		(Category::FixedBaseline, &outcome.fixed_baseline),
+	] {
+		for (&id, deps) in deps_by_member {
+			for kind in [DepKind::Normal, DepKind::Development, DepKind::Build] {
+				for dep in deps.unused_deps(kind) {
+					findings.push(Finding {
+						category,
+						severity : None,
+						package : package_info(outcome, id),
+						dependency : dependency_info(outcome, id, kind, dep),
+						ignore_reason : None,
+						evaluated_in : Vec::new(),
+						savings : None,
+					});
+				}
+			}
+		}
+	}
+	for (&id, deps) in &outcome.ignored_deps {
+		for kind in [DepKind::Normal, DepKind::Development, DepKind::Build] {
+			for (dep, reason) in deps.ignored_deps(kind) {
+				findings.push(Finding {
+					category : Category::Ignored,
+					severity : None,
+					package : package_info(outcome, id),
+					dependency : dependency_info(outcome, id, kind, dep),
+					ignore_reason : reason.clone(),
+					evaluated_in : Vec::new(),
+					savings : None,
+				});
+			}
+		}
+	}
+
+	// Unused dependencies with the largest savings first. The sort is stable,
+	// and only unused dependencies have savings, so the categories stay in order.
+	findings.sort_by_key(|finding| Reverse(finding.savings.as_ref().map_or(0, |savings| savings.removed_packages)));
+	findings
+}
+
+fn savings_info(outcome :&Outcome, id :PackageId, kind :DepKind, name_in_toml :InternedString) -> Option<SavingsInfo> {
+	let savings = outcome.savings.get(&(id, kind, name_in_toml))?;
+	Some(SavingsInfo {
+		removed_packages : savings.packages.len(),
+		packages : savings.packages
+			.iter()
+			.map(|pkg| format!("{}@{}", pkg.name(), pkg.version()))
+			.collect(),
+		compile_time_secs : savings.compile_time.map(|time| time.as_secs_f64()),
+	})
+}
+
+fn package_info(outcome :&Outcome, id :PackageId) -> PackageInfo {
+	PackageInfo {
+		id : id.to_spec().to_string(),
+		name : id.name().to_string(),
+		version : id.version().to_string(),
+		source : id.source_id().as_url().to_string(),
+		manifest_path : outcome.packages
+			.get(&id)
+			.map(|package| package.manifest_path().display().to_string())
+			.unwrap_or_default(),
+	}
+}
+
+fn dependency_info(outcome :&Outcome, id :PackageId, kind :DepKind, name_in_toml :&str) -> DependencyInfo {
+	let declarations = outcome.packages
+		.get(&id)
+		.map(|package| {
+			package.dependencies()
+				.iter()
+				.filter(|dep| dep.kind() == kind && dep.name_in_toml() == name_in_toml)
+				.collect::<Vec<_>>()
+		})
+		.unwrap_or_default();
+	let location = outcome.manifests
+		.get(&id)
+		.and_then(|manifest| {
+			let span = manifest.dependency(kind, name_in_toml)?.span.as_ref()?;
+			let (line, column) = manifest.line_column(span.start);
+			Some(Location { line, column })
+		});
+	DependencyInfo {
+		name_in_toml : name_in_toml.to_owned(),
+		package_name : declarations.first().map(|dep| dep.package_name().to_string()),
+		kind : kind_name(kind),
+		optional : declarations.iter().any(|dep| dep.is_optional()),
+		// If any declaration applies to all targets, so does the dependency.
+		target : declarations
+			.iter()
+			.map(|dep| dep.platform())
+			.collect::<Option<Vec<_>>>()
+			.and_then(|platforms| platforms.first().map(|platform| platform.to_string())),
+		location,
+	}
+}
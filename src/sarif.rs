@@ -0,0 +1,99 @@
+//! `--output sarif`: SARIF 2.1.0, for code scanning tools.
+//!
+//! <https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html>
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use cargo::core::dependency::DepKind;
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::{Outcome, Severity};
+
+const RULES :&[(DepKind, &str, &str)] = &[
+	(DepKind::Normal, "unused-dependency", "Unused dependency"),
+	(DepKind::Development, "unused-dev-dependency", "Unused dev-dependency"),
+	(DepKind::Build, "unused-build-dependency", "Unused build-dependency"),
+];
+
+pub(crate) fn print(outcome :&Outcome, mut stdout :impl Write) -> io::Result<()> {
+	let root = Url::from_directory_path(&outcome.workspace_root).ok();
+	let mut results = Vec::new();
+	for (id, deps) in &outcome.unused_deps {
+		let manifest = outcome.manifests.get(id);
+		for &(kind, rule_id, description) in RULES {
+			let level = match outcome.severity.unused(kind) {
+				Severity::Deny => "error",
+				Severity::Warn => "warning",
+				Severity::Allow => continue,
+			};
+			for dep in deps.unused_deps(kind) {
+				let mut physical_location = json!({
+					"artifactLocation": artifact_location(root.as_ref(), Path::new(&deps.manifest_path)),
+				});
+				let span = manifest
+					.and_then(|manifest| Some((manifest, manifest.dependency(kind, dep)?.span.clone()?)));
+				if let Some((manifest, span)) = span {
+					let (start_line, start_column) = manifest.line_column(span.start);
+					let (end_line, end_column) = manifest.line_column(span.end);
+					physical_location["region"] = json!({
+						"startLine": start_line,
+						"startColumn": start_column,
+						"endLine": end_line,
+						"endColumn": end_column,
+					});
+				}
+				results.push(json!({
+					"ruleId": rule_id,
+					"level": level,
+					"message": {
+						"text": format!("{} `{}` of `{}`", description, dep, id),
+					},
+					"locations": [{ "physicalLocation": physical_location }],
+				}));
+			}
+		}
+	}
+
+	let rules = RULES
+		.iter()
+		.map(|(_, id, description)| json!({
+			"id": id,
+			"shortDescription": { "text": description },
+		}))
+		.collect::<Vec<_>>();
+	let mut run = json!({
+		"tool": {
+			"driver": {
+				"name": "cargo-udeps",
+				"version": env!("CARGO_PKG_VERSION"),
+				"informationUri": "https://github.com/est31/cargo-udeps",
+				"rules": rules,
+			},
+		},
+		"columnKind": "unicodeCodePoints",
+		"results": results,
+	});
+	if let Some(root) = &root {
+		run["originalUriBaseIds"] = json!({ "%SRCROOT%": { "uri": root.as_str() } });
+	}
+	let sarif = json!({
+		"$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+		"version": "2.1.0",
+		"runs": [run],
+	});
+	writeln!(stdout, "{}", sarif)?;
+	stdout.flush()
+}
+
+/// The location of `path`, relative to the workspace root if possible.
+fn artifact_location(root :Option<&Url>, path :&Path) -> Value {
+	let url = Url::from_file_path(path).ok();
+	let relative = root.zip(url.as_ref()).and_then(|(root, url)| root.make_relative(url));
+	match (relative, url) {
+		(Some(relative), _) if !relative.starts_with("../") => json!({ "uri": relative, "uriBaseId": "%SRCROOT%" }),
+		(_, Some(url)) => json!({ "uri": url.as_str() }),
+		(_, None) => json!({ "uri": path.to_string_lossy() }),
+	}
+}
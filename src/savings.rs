@@ -0,0 +1,77 @@
+//! Estimating what removing an unused dependency would save.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::time::Duration;
+
+use cargo::core::dependency::DepKind;
+use cargo::core::package_id::PackageId;
+use cargo::core::Resolve;
+use cargo::util::interning::InternedString;
+
+/// What removing an unused dependency would save.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Savings {
+	/// The packages that would leave the resolve, the dependency itself included
+	pub(crate) packages :BTreeSet<PackageId>,
+	/// The dependency edges between these packages
+	pub(crate) edges :BTreeSet<(PackageId, PackageId)>,
+	/// The time it took to compile these packages in this run, with `--compile-times`.
+	///
+	/// Packages that were fresh don't count, so this is a lower bound.
+	pub(crate) compile_time :Option<Duration>,
+}
+
+/// The packages that are only reachable from `roots` through the dependency `name_in_toml` of `from`.
+pub(crate) fn removed_packages(
+	resolve :&Resolve,
+	roots :&[PackageId],
+	from :PackageId,
+	kind :DepKind,
+	name_in_toml :InternedString,
+) -> BTreeSet<PackageId> {
+	let before = reachable(resolve, roots, |_, _| true);
+	let after = reachable(resolve, roots, |pkg, deps| {
+		pkg != from || deps.iter().any(|dep| dep.kind() != kind || dep.name_in_toml() != name_in_toml)
+	});
+	before.difference(&after).copied().collect()
+}
+
+/// The dependency edges between `packages`.
+pub(crate) fn edges(resolve :&Resolve, packages :&BTreeSet<PackageId>) -> BTreeSet<(PackageId, PackageId)> {
+	packages
+		.iter()
+		.flat_map(|&pkg| resolve.deps(pkg).map(move |(dep_pkg, _)| (pkg, dep_pkg)))
+		.filter(|(_, dep_pkg)| packages.contains(dep_pkg))
+		.collect()
+}
+
+/// The packages reachable from `roots` through the edges for which `follow` returns true.
+fn reachable(
+	resolve :&Resolve,
+	roots :&[PackageId],
+	follow :impl Fn(PackageId, &HashSet<cargo::core::Dependency>) -> bool,
+) -> HashSet<PackageId> {
+	let mut reachable = roots.iter().copied().collect::<HashSet<_>>();
+	let mut queue = roots.to_vec();
+	while let Some(pkg) = queue.pop() {
+		for (dep_pkg, deps) in resolve.deps(pkg) {
+			if follow(pkg, deps) && reachable.insert(dep_pkg) {
+				queue.push(dep_pkg);
+			}
+		}
+	}
+	reachable
+}
+
+/// Sums the compile times of `packages`.
+///
+/// Returns `None` if none of them were compiled in this run.
+pub(crate) fn compile_time(
+	packages :&BTreeSet<PackageId>,
+	compile_times :&HashMap<PackageId, Duration>,
+) -> Option<Duration> {
+	packages
+		.iter()
+		.filter_map(|pkg| compile_times.get(pkg))
+		.fold(None, |sum, &time| Some(sum.unwrap_or_default() + time))
+}
@@ -0,0 +1,175 @@
+//! `--watch`: analyzing the workspace again whenever one of its files changes.
+//!
+//! The watched files are the manifests and the sources that the backend reported for each member.
+//! If only sources changed, only the members they belong to are built and analyzed again.
+//! The files are polled, which works the same everywhere.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use cargo::core::package_id::PackageId;
+use cargo::util::context::GlobalContext;
+use cargo::CliError;
+
+use crate::api::cli_error;
+use crate::{config, diff, AnalysisOptions, Incremental, Outcome, OutputKind};
+
+const POLL_INTERVAL :Duration = Duration::from_millis(500);
+
+/// The modification times of the watched files, `None` for missing ones.
+pub(crate) type Snapshot = BTreeMap<PathBuf, Option<SystemTime>>;
+
+/// The files of an analyzed workspace.
+pub(crate) struct WatchedFiles {
+	/// The sources of each member
+	sources :BTreeMap<PathBuf, BTreeSet<PackageId>>,
+	/// Manifests, the lock file and configuration files, which affect all members
+	shared :BTreeSet<PathBuf>,
+}
+
+impl WatchedFiles {
+	pub(crate) fn new(outcome :&Outcome) -> Self {
+		let mut sources = BTreeMap::<_, BTreeSet<_>>::new();
+		for (&id, member_sources) in &outcome.sources {
+			for source in member_sources {
+				sources.entry(source.clone()).or_default().insert(id);
+			}
+		}
+		let mut shared = outcome.packages
+			.values()
+			.map(|package| package.manifest_path().to_owned())
+			.collect::<BTreeSet<_>>();
+		let root = &outcome.workspace_root;
+		shared.extend(["Cargo.toml", "Cargo.lock"].map(|name| root.join(name)));
+		for package in outcome.packages.values() {
+			for dir in config::dirs(package.root(), root) {
+				shared.extend(config::FILE_NAMES.iter().map(|name| dir.join(name)));
+			}
+		}
+		Self { sources, shared }
+	}
+
+	pub(crate) fn snapshot(&self) -> Snapshot {
+		self.sources
+			.keys()
+			.chain(&self.shared)
+			.map(|path| (path.clone(), modified(path)))
+			.collect()
+	}
+
+	/// The snapshot of the files as the analysis saw them, so that the ones saved
+	/// during the analysis are seen as changed.
+	///
+	/// `before` was taken when the analysis `started`.
+	pub(crate) fn snapshot_seen(&self, before :&Snapshot, started :SystemTime) -> Snapshot {
+		self.snapshot()
+			.into_iter()
+			.map(|(path, modified)| {
+				let seen = match before.get(&path) {
+					Some(&seen) => seen,
+					// Unknown before the analysis, only changed if saved during it
+					None => modified.filter(|&modified| modified < started),
+				};
+				(path, seen)
+			})
+			.collect()
+	}
+
+	/// The members to analyze again after `paths` changed, `None` for all of them.
+	pub(crate) fn affected_members(&self, paths :&[PathBuf]) -> Option<HashSet<PackageId>> {
+		let mut members = HashSet::new();
+		for path in paths {
+			members.extend(self.sources.get(path).filter(|_| !self.shared.contains(path))?);
+		}
+		Some(members)
+	}
+}
+
+pub(crate) fn run(
+	options :&AnalysisOptions,
+	config :&mut GlobalContext,
+	mut stdout :impl Write,
+) -> Result<(), CliError> {
+	let mut incremental = Incremental::default();
+	let mut previous = None;
+	let mut files = None;
+	loop {
+		let started = SystemTime::now();
+		let before = files.as_ref().map(WatchedFiles::snapshot).unwrap_or_default();
+		match options.outcome_incremental(config, &mut incremental, Some(&mut stdout)) {
+			Ok(outcome) => {
+				options.print(&outcome, &mut stdout)?;
+				let unused_deps = diff::unused_deps_of(&outcome);
+				if let Some(previous) = &previous {
+					let mut changes = Vec::new();
+					writeln!(changes, "Changes since the previous run:")?;
+					diff::print_changes(previous, &unused_deps, &mut changes)?;
+					// Other formats are meant for tools, keep them parseable
+					if matches!(outcome.options.output, OutputKind::Human) {
+						stdout.write_all(&changes)?;
+						stdout.flush()?;
+					} else {
+						config.shell().err().write_all(&changes)?;
+					}
+				}
+				previous = Some(unused_deps);
+				files = Some(WatchedFiles::new(&outcome));
+			},
+			Err(err) => {
+				// Without a successful run, there is nothing to watch
+				if files.is_none() {
+					return Err(err);
+				}
+				cargo::display_error(&cli_error(err), &mut config.shell());
+				// The analyses may be outdated, or there may be none for some members
+				incremental.analyses.clear();
+			},
+		}
+
+		let files = files.as_ref().expect("set after a successful run");
+		let snapshot = files.snapshot_seen(&before, started);
+		config.shell().status("Watching", format_args!("{} files for changes", snapshot.len()))?;
+		let changed = wait_for_changes(files, &snapshot);
+		if !incremental.analyses.is_empty() {
+			incremental.changed = files.affected_members(&changed);
+		}
+	}
+}
+
+/// Waits until some files are changed, and returns them.
+fn wait_for_changes(files :&WatchedFiles, snapshot :&Snapshot) -> Vec<PathBuf> {
+	loop {
+		thread::sleep(POLL_INTERVAL);
+		let mut current = files.snapshot();
+		if current == *snapshot {
+			continue;
+		}
+		// Editors may save several files at once, wait until they are done
+		loop {
+			thread::sleep(POLL_INTERVAL);
+			let next = files.snapshot();
+			if next == current {
+				break;
+			}
+			current = next;
+		}
+		return changes(snapshot, current);
+	}
+}
+
+/// The files whose modification time in `current` differs from the one in `snapshot`.
+pub(crate) fn changes(snapshot :&Snapshot, current :Snapshot) -> Vec<PathBuf> {
+	current
+		.into_iter()
+		.filter(|(path, modified)| snapshot.get(path) != Some(modified))
+		.map(|(path, _)| path)
+		.collect()
+}
+
+fn modified(path :&Path) -> Option<SystemTime> {
+	fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,93 @@
+mod runner;
+
+use cargo::CargoResult;
+use cargo_udeps::{DependencyKind, DependencyVerdict, Severity, Udeps};
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "api"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+development = ["maplit"]
+
+[dependencies]
+bo = { package = "byteorder", version = "1.0.0" }
+if_chain = "1.0.0"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+static LIB_RS: &str = "use bo as _;\n";
+
+#[test]
+fn api() -> CargoResult<()> {
+	let report =
+		Runner::new("cargo_udeps_test_api")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.run_api(Udeps::new().all_targets())?;
+	assert!(!report.success);
+	assert_eq!(
+		vec![
+			("bo", "byteorder", DependencyKind::Normal, DependencyVerdict::Used),
+			("if_chain", "if_chain", DependencyKind::Normal, DependencyVerdict::Unused { severity : Severity::Deny }),
+			("maplit", "maplit", DependencyKind::Development, DependencyVerdict::Ignored { reason : None }),
+		],
+		report.dependencies
+			.iter()
+			.map(|dep| (&*dep.name_in_toml, &*dep.package_name, dep.kind, dep.verdict.clone()))
+			.collect::<Vec<_>>(),
+	);
+	assert!(report.dependencies.iter().all(|dep| dep.member == "api" && dep.member_version == "0.1.0"));
+	assert_eq!(vec!["lib:api"], report.dependencies[0].used_by);
+	assert_eq!(vec!["if_chain"], report.unused().map(|dep| &*dep.name_in_toml).collect::<Vec<_>>());
+	Ok(())
+}
+
+#[test]
+fn api_options() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "api-options"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[features]
+web = ["if_chain"]
+other = []
+
+[dependencies]
+if_chain = { version = "1.0.0", optional = true }
+"#;
+
+	let verdicts = |udeps :Udeps| -> CargoResult<_> {
+		let report = Runner::new("cargo_udeps_test_api_options")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "#[cfg(feature = \"web\")]\nuse if_chain as _;\n")?
+			.file("./udeps.toml", "features = [\"web\"]\n")?
+			.run_api(udeps)?;
+		Ok(report.dependencies
+			.into_iter()
+			.map(|dep| (dep.name_in_toml, dep.verdict))
+			.collect::<Vec<_>>())
+	};
+	// The configuration applies to the options the builder didn't set
+	assert_eq!(vec![("if_chain".to_owned(), DependencyVerdict::Used)], verdicts(Udeps::new())?);
+	assert_eq!(
+		vec![("if_chain".to_owned(), DependencyVerdict::NotEvaluated)],
+		verdicts(Udeps::new().feature("other"))?,
+	);
+	Ok(())
+}
@@ -0,0 +1,106 @@
+#![cfg(unix)]
+
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "backend"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+bo = { package = "byteorder", version = "1.0.0" }
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS: &str = "use bo as _;\n";
+
+// Claims that `if_chain` is used and `bo` is not, regardless of the source
+static BACKEND_SH: &str = r#"#!/bin/sh
+cat > request.json
+echo '{"used":[{"extern_crate_name":"if_chain"}]}'
+"#;
+
+static FAILING_BACKEND_SH: &str = r#"#!/bin/sh
+echo 'something went wrong' >&2
+exit 3
+"#;
+
+#[test]
+fn external() -> CargoResult<()> {
+	let (code, output, request) =
+		Runner::new("cargo_udeps_test_backend_external")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.executable("backend.sh", BACKEND_SH)?
+			.arg("--backend")
+			.arg("external")
+			.arg("--backend-command")
+			.arg("./backend.sh")
+			.run_and_read("request.json")?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`backend v0.1.0 (██████████)`
+└─── dependencies
+     └─── "bo"
+Note: These dependencies might be used by other targets.
+      To find dependencies that are not used by any target, enable `--all-targets`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		output,
+	);
+
+	let request = serde_json::from_str::<serde_json::Value>(&request)?;
+	assert_eq!(1, request["version"]);
+	assert_eq!("lib", request["target_kind"]);
+	assert_eq!("backend", request["target_name"]);
+	assert_eq!("backend", request["crate_name"]);
+	assert_eq!(serde_json::json!(["bo", "if_chain"]), request["extern_crate_names"]);
+	assert!(request["package_id"].as_str().unwrap().contains("backend"));
+	Ok(())
+}
+
+#[test]
+fn external_failure() -> CargoResult<()> {
+	let err =
+		Runner::new("cargo_udeps_test_backend_external_failure")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.executable("backend.sh", FAILING_BACKEND_SH)?
+			.arg("--backend")
+			.arg("external")
+			.arg("--backend-command")
+			.arg("./backend.sh")
+			.run()
+			.expect_err("should fail");
+	let message = format!("{:#}", err);
+	assert!(message.contains("exit status: 3: something went wrong"), "{}", message);
+	Ok(())
+}
+
+#[test]
+fn external_without_command() -> CargoResult<()> {
+	let err =
+		Runner::new("cargo_udeps_test_backend_external_without_command")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--backend")
+			.arg("external")
+			.run()
+			.expect_err("should fail");
+	assert_eq!("the `external` backend needs `--backend-command`", format!("{:#}", err));
+	Ok(())
+}
@@ -0,0 +1,174 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "baseline"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+matches = "0.1.8"
+"#;
+
+static LIB_RS: &str = "";
+
+#[test]
+fn write_baseline() -> CargoResult<()> {
+	let (code, _, baseline) =
+		Runner::new("cargo_udeps_test_baseline_write_baseline")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--write-baseline")
+			.arg("udeps-baseline.json")
+			.run_and_read("udeps-baseline.json")?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"{
+  "version": 1,
+  "unused": {
+    "baseline": {
+      "normal": [
+        "if_chain",
+        "matches"
+      ]
+    }
+  }
+}
+"#,
+		baseline,
+	);
+	Ok(())
+}
+
+#[test]
+fn baseline() -> CargoResult<()> {
+	static BASELINE: &str = r#"{
+  "version": 1,
+  "unused": {
+    "baseline": {
+      "normal": ["matches", "maplit"]
+    },
+    "other": {
+      "normal": ["log"]
+    }
+  }
+}
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_baseline_baseline")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.file("udeps-baseline.json", BASELINE)?
+			.arg("--baseline")
+			.arg("udeps-baseline.json")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`baseline v0.0.1 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+Note: These dependencies might be used by other targets.
+      To find dependencies that are not used by any target, enable `--all-targets`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+1 finding(s) are accepted by the baseline.
+fixed since the baseline (remove them from it):
+`baseline v0.0.1 (██████████)`
+└─── dependencies
+     └─── "maplit"
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn baseline_accepts_all() -> CargoResult<()> {
+	static BASELINE: &str = r#"{"version":1,"unused":{"baseline":{"normal":["if_chain","matches"]}}}"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_baseline_baseline_accepts_all")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.file("udeps-baseline.json", BASELINE)?
+			.arg("--baseline")
+			.arg("udeps-baseline.json")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		"All deps seem to have been used.\n2 finding(s) are accepted by the baseline.\n",
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn baseline_package() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+	static MEMBER_CARGO_TOML: &str = r#"[package]
+name = "{}"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+matches = "0.1.8"
+"#;
+
+	static BASELINE: &str = r#"{"version":1,"unused":{"a":{"normal":["matches"]},"b":{"normal":["matches"]}}}"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_baseline_baseline_package")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./a/src")?
+			.file("./a/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "a"))?
+			.file("./a/src/lib.rs", LIB_RS)?
+			.dir("./b/src")?
+			.file("./b/Cargo.toml", &MEMBER_CARGO_TOML.replace("{}", "b"))?
+			.file("./b/src/lib.rs", LIB_RS)?
+			.file("udeps-baseline.json", BASELINE)?
+			.arg("--baseline")
+			.arg("udeps-baseline.json")
+			.arg("-p")
+			.arg("a")
+			.run()?;
+	assert_eq!(0, code);
+	// The entry of `b` isn't reported as fixed, `b` wasn't analyzed
+	assert_eq!(
+		"All deps seem to have been used.\n1 finding(s) are accepted by the baseline.\n",
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn invalid_baseline() -> CargoResult<()> {
+	let err = Runner::new("cargo_udeps_test_baseline_invalid_baseline")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.file("udeps-baseline.json", r#"{"version":2}"#)?
+		.arg("--baseline")
+		.arg("udeps-baseline.json")
+		.run()
+		.expect_err("should fail");
+	let err = format!("{:#}", err);
+	assert!(err.contains("has version 2, but only version 1 is supported"), "{}", err);
+	Ok(())
+}
@@ -31,3 +31,90 @@ fn cfg_test_no_unexpected_cfg() -> CargoResult<()> {
 	assert_eq!("All deps seem to have been used.\n", stdout_masked);
 	Ok(())
 }
+
+static CARGO_TOML_DECLARED_USED :&str = r#"[workspace]
+[package]
+name = "declared-used"
+version = "0.0.1"
+edition = "2018"
+[dependencies]
+byteorder = "1.0.0"
+"#;
+
+static LIB_RS_DECLARED_USED :&str = r#"
+#![deny(unexpected_cfgs)]
+#[cfg(cargo_udeps)]
+use byteorder as _;
+"#;
+
+#[test]
+fn cfg_cargo_udeps_declared_used() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_cfg_cargo_udeps_declared_used")?
+			.cargo_toml(CARGO_TOML_DECLARED_USED)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS_DECLARED_USED)?
+			.arg("--all-targets")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"All deps seem to have been used.
+dependencies declared as used:
+`declared-used v0.0.1 (██████████)`
+└─── dependencies
+     └─── "byteorder"
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+static CARGO_TOML_COMMENTED_OUT :&str = r#"[workspace]
+[package]
+name = "commented-out"
+version = "0.0.1"
+edition = "2018"
+[dependencies]
+byteorder = "1.0.0"
+if_chain = "1.0.0"
+"#;
+
+static LIB_RS_COMMENTED_OUT :&str = r##"
+#![deny(unexpected_cfgs)]
+// #[cfg(cargo_udeps)] use byteorder as _;
+/// `#[cfg(cargo_udeps)] use byteorder as _;`
+pub const S :&str = "#[cfg(cargo_udeps)] use byteorder as _;";
+pub const R :&str = r#"#[cfg(cargo_udeps)] use byteorder as _;"#;
+pub fn f<'a>(s :&'a str) -> (&'a str, char) { (s, '"') }
+/* #[cfg(cargo_udeps)] /* nested */ use byteorder as _; */
+#[cfg(cargo_udeps)]
+use if_chain as _;
+"##;
+
+#[test]
+fn cfg_cargo_udeps_commented_out() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_cfg_cargo_udeps_commented_out")?
+			.cargo_toml(CARGO_TOML_COMMENTED_OUT)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS_COMMENTED_OUT)?
+			.arg("--all-targets")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`commented-out v0.0.1 (██████████)`
+└─── dependencies
+     └─── "byteorder"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+dependencies declared as used:
+`commented-out v0.0.1 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,261 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "config-file"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+matches = "0.1.8"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+static LIB_RS: &str = "";
+
+#[test]
+fn udeps_toml() -> CargoResult<()> {
+	static UDEPS_TOML: &str = r#"all-targets = true
+
+[ignore]
+normal = ["if_chain"]
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_config_file_udeps_toml")?
+			.cargo_toml(CARGO_TOML)?
+			.file("udeps.toml", UDEPS_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`config-file v0.0.0 (██████████)`
+├─── dependencies
+│    └─── "matches"
+└─── dev-dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn dot_cargo_udeps_toml_package_section() -> CargoResult<()> {
+	static UDEPS_TOML: &str = r#"all-targets = true
+output = "json"
+
+[package.config-file.ignore]
+normal = ["if_chain", "matches"]
+development = ["maplit"]
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_config_file_dot_cargo_udeps_toml_package_section")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./.cargo")?
+			.file("./.cargo/udeps.toml", UDEPS_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		concat!(
+			r#"{"success":true,"unused_deps":{},"note":null,"#,
+			r#""ignored_deps":{"config-file 0.0.0 (path+file://██████████)":"#,
+			r#"{"normal":{"if_chain":null,"matches":null},"development":{"maplit":null},"build":{}}}}"#,
+			"\n",
+		),
+		stdout_masked,
+	);
+	Ok(())
+}
+
+static WORKSPACE_METADATA_CARGO_TOML: &str = r#"[workspace]
+
+[workspace.metadata.cargo-udeps]
+all-targets = true
+output = "json-v2"
+
+[workspace.metadata.cargo-udeps.ignore]
+normal = ["if_chain", "matches"]
+
+[package]
+name = "config-file"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+matches = "0.1.8"
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+#[test]
+fn workspace_metadata_options() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_config_file_workspace_metadata_options")?
+			.cargo_toml(WORKSPACE_METADATA_CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.run()?;
+	assert_eq!(1, code);
+	let report = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!(true, report["options"]["all_targets"]);
+	assert_eq!("json-v2", report["options"]["output"]);
+	let findings = report["findings"].as_array().unwrap();
+	assert!(findings.iter().any(|finding| finding["category"] == "unused"
+		&& finding["dependency"]["name_in_toml"] == "maplit"
+		&& finding["dependency"]["kind"] == "development"), "{}", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn workspace_metadata_options_overridden() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_config_file_workspace_metadata_options_overridden")?
+			.cargo_toml(WORKSPACE_METADATA_CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--output")
+			.arg("human")
+			.arg("--lib")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn workspace_metadata_profile_overridden_by_release() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+
+[workspace.metadata.cargo-udeps]
+profile = "test"
+output = "json-v2"
+
+[package]
+name = "config-file"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_config_file_workspace_metadata_profile_overridden_by_release")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "#[cfg(test)]\nuse if_chain as _;\n")?
+			.arg("--release")
+			.run()?;
+	assert_eq!(1, code);
+	let report = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!("release", report["options"]["profile"]);
+	assert_eq!("unused", report["findings"][0]["category"]);
+	assert_eq!("if_chain", report["findings"][0]["dependency"]["name_in_toml"]);
+	Ok(())
+}
+
+static HIERARCHY_CARGO_TOML: &str = r#"[workspace]
+members = ["crates/a", "crates/b", "tools/c"]
+"#;
+
+fn hierarchy_member(name :&str) -> String {
+	format!(r#"[package]
+name = "{}"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+matches = "0.1.8"
+"#, name)
+}
+
+fn hierarchy_runner(prefix :&str) -> CargoResult<Runner> {
+	let mut runner = Runner::new(prefix)?.cargo_toml(HIERARCHY_CARGO_TOML)?;
+	for (dir, name) in [("crates/a", "a"), ("crates/b", "b"), ("tools/c", "c")] {
+		runner = runner
+			.dir(&format!("{}/src", dir))?
+			.file(&format!("{}/Cargo.toml", dir), &hierarchy_member(name))?
+			.file(&format!("{}/src/lib.rs", dir), LIB_RS)?;
+	}
+	Ok(runner)
+}
+
+#[test]
+fn hierarchy_ignores() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		hierarchy_runner("cargo_udeps_test_config_file_hierarchy_ignores")?
+			.file("crates/udeps.toml", "[ignore]\nnormal = [\"if_chain\"]\n")?
+			.file("crates/a/udeps.toml", "[ignore]\nnormal = [\"matches\"]\n")?
+			.arg("--all-targets")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`b v0.0.0 (██████████/crates/b)`
+└─── dependencies
+     └─── "matches"
+`c v0.0.0 (██████████/tools/c)`
+└─── dependencies
+     ├─── "if_chain"
+     └─── "matches"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn hierarchy_options() -> CargoResult<()> {
+	static ROOT_UDEPS_TOML: &str = r#"all-targets = true
+output = "json"
+
+[severity]
+unused = "warn"
+"#;
+
+	let runner = |prefix, dir| -> CargoResult<(i32, String)> {
+		hierarchy_runner(prefix)?
+			.file("udeps.toml", ROOT_UDEPS_TOML)?
+			.file("crates/udeps.toml", "output = \"human\"\n\n[severity]\nnormal = \"deny\"\n")?
+			.current_dir(dir)
+			.arg("--package")
+			.arg("c")
+			.run()
+	};
+
+	// The nearer file takes precedence, but only if it is on the way to the workspace root
+	let (code, stdout_masked) = runner("cargo_udeps_test_config_file_hierarchy_options_crates", "crates/a")?;
+	assert_eq!(1, code);
+	assert!(stdout_masked.starts_with("unused dependencies:\n`c v0.0.0"), "{}", stdout_masked);
+	let (code, stdout_masked) = runner("cargo_udeps_test_config_file_hierarchy_options_tools", "tools/c")?;
+	assert_eq!(0, code);
+	assert!(stdout_masked.starts_with(r#"{"success":true,"#), "{}", stdout_masked);
+	Ok(())
+}
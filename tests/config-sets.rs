@@ -0,0 +1,87 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[workspace.metadata.cargo-udeps.configs.web]
+features = ["web"]
+
+[workspace.metadata.cargo-udeps.configs.native]
+
+[package]
+name = "config-sets"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[features]
+web = []
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+matches = "0.1.8"
+"#;
+
+static LIB_RS: &str = r#"#[cfg(feature = "web")]
+use maplit as _;
+#[cfg(not(feature = "web"))]
+use matches as _;
+"#;
+
+#[test]
+fn all_config_sets() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_config_sets_all_config_sets")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--config-set")
+			.arg("all")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`config-sets v0.0.0 (██████████)`
+└─── dependencies
+     └─── "if_chain" (in native, web)
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn single_config_set() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_config_sets_single_config_set")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--config-set")
+			.arg("web")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`config-sets v0.0.0 (██████████)`
+└─── dependencies
+     ├─── "if_chain" (in web)
+     └─── "matches" (in web)
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
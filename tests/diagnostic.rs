@@ -0,0 +1,193 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+members = ["foo"]
+
+[workspace.dependencies]
+maplit = "1.0.2"
+"#;
+
+static FOO_CARGO_TOML: &str = r#"[package]
+name = "foo"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[dev-dependencies]
+maplit = { workspace = true }
+
+[build-dependencies.matches]
+version = "0.1.8"
+"#;
+
+static LIB_RS: &str = "";
+static BUILD_RS: &str = "fn main() {}\n";
+
+#[test]
+fn diagnostic() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_diagnostic")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./foo/src")?
+			.file("./foo/Cargo.toml", FOO_CARGO_TOML)?
+			.file("./foo/src/lib.rs", LIB_RS)?
+			.file("./foo/build.rs", BUILD_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("diagnostic")
+			.arg("--severity")
+			.arg("development=warn")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"error: unused dependency `if_chain`
+ --> foo/Cargo.toml:8:1
+  |
+8 | if_chain = "1.0.0"
+  | ^^^^^^^^^^^^^^^^^^
+  |
+  = help: remove this line
+
+warning: unused dev-dependency `maplit`
+  --> foo/Cargo.toml:11:1
+   |
+11 | maplit = { workspace = true }
+   | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^
+   |
+   = note: `maplit` is inherited from `[workspace.dependencies]`, which can be removed too if no other member uses it
+   = help: remove this line
+
+error: unused build-dependency `matches`
+  --> foo/Cargo.toml:13:21
+   |
+13 | [build-dependencies.matches]
+   |                     ^^^^^^^^
+   |
+   = help: remove the `matches` table
+
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn rustc_json() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_diagnostic_rustc_json")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./foo/src")?
+			.file("./foo/Cargo.toml", FOO_CARGO_TOML)?
+			.file("./foo/src/lib.rs", LIB_RS)?
+			.file("./foo/build.rs", BUILD_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("rustc-json")
+			.run()?;
+	assert_eq!(1, code);
+	let messages = stdout_masked
+		.lines()
+		.map(serde_json::from_str::<serde_json::Value>)
+		.collect::<Result<Vec<_>, _>>()?;
+	assert_eq!(3, messages.len());
+
+	// Apply the suggestions in reverse order, like rustfix does.
+	let mut manifest = FOO_CARGO_TOML.to_owned();
+	let mut suggestions = Vec::new();
+	for message in &messages {
+		assert_eq!("compiler-message", message["reason"]);
+		assert_eq!("lib", message["target"]["kind"][0]);
+		let diagnostic = &message["message"];
+		assert_eq!("error", diagnostic["level"]);
+		assert_eq!("foo/Cargo.toml", diagnostic["spans"][0]["file_name"]);
+		let help = &diagnostic["children"][0];
+		assert_eq!("help", help["level"]);
+		let span = &help["spans"][0];
+		assert_eq!("", span["suggested_replacement"]);
+		suggestions.push((
+			span["byte_start"].as_u64().unwrap() as usize,
+			span["byte_end"].as_u64().unwrap() as usize,
+			span["suggestion_applicability"].as_str().unwrap().to_owned(),
+		));
+	}
+	suggestions.sort();
+	for (start, end, _) in suggestions.iter().rev() {
+		manifest.replace_range(start..end, "");
+	}
+	assert_eq!(
+		vec!["MachineApplicable", "MachineApplicable", "MaybeIncorrect"],
+		suggestions.iter().map(|(_, _, applicability)| applicability.as_str()).collect::<Vec<_>>(),
+	);
+	assert_eq!(
+		r#"[package]
+name = "foo"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+
+[dev-dependencies]
+
+"#,
+		manifest,
+	);
+	Ok(())
+}
+
+#[test]
+fn rust_analyzer() -> CargoResult<()> {
+	// Like rust-analyzer's `check.overrideCommand`
+	let (code, stdout_masked, stderr) =
+		Runner::new("cargo_udeps_test_diagnostic_rust_analyzer")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./foo/src")?
+			.file("./foo/Cargo.toml", FOO_CARGO_TOML)?
+			.file("./foo/src/lib.rs", "fn unused() {}\n")?
+			.file("./foo/build.rs", BUILD_RS)?
+			.arg("--workspace")
+			.arg("--all-targets")
+			.arg("--message-format")
+			.arg("json-diagnostic-short")
+			.arg("--output")
+			.arg("rustc-json")
+			.run_with_stderr()?;
+	assert_eq!(1, code);
+
+	// The messages of rustc are passed through, here to the shell
+	let rustc_message = stderr
+		.lines()
+		.filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+		.find(|message| message["reason"] == "compiler-message")
+		.expect("should forward the warning of rustc");
+	assert_eq!("dead_code", rustc_message["message"]["code"]["code"]);
+
+	let rendered = stdout_masked
+		.lines()
+		.map(serde_json::from_str::<serde_json::Value>)
+		.collect::<Result<Vec<_>, _>>()?
+		.into_iter()
+		.filter(|message| message["reason"] == "compiler-message")
+		.map(|message| message["message"]["rendered"].as_str().unwrap().to_owned())
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![
+			"foo/Cargo.toml:8:1: error: unused dependency `if_chain`\n",
+			"foo/Cargo.toml:11:1: error: unused dev-dependency `maplit`\n",
+			"foo/Cargo.toml:13:21: error: unused build-dependency `matches`\n",
+		],
+		rendered,
+	);
+	Ok(())
+}
@@ -0,0 +1,98 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+// `--output json`
+static OLD_JSON: &str = r#"{"success":false,"unused_deps":{
+"foo 0.1.0 (path+file:///old/foo)":{"manifest_path":"/old/foo/Cargo.toml","normal":["if_chain","matches"],"development":["maplit"],"build":[]},
+"bar 0.1.0 (path+file:///old/bar)":{"manifest_path":"/old/bar/Cargo.toml","normal":["log"],"development":[],"build":[]}
+},"note":null}
+"#;
+
+// `--output json-v2`
+static NEW_JSON: &str = r#"{"version":2,"success":false,"findings":[
+{"category":"unused","severity":"deny","package":{"name":"foo"},"dependency":{"name_in_toml":"if_chain","kind":"normal"}},
+{"category":"unused","severity":"deny","package":{"name":"foo"},"dependency":{"name_in_toml":"maplit","kind":"normal"}},
+{"category":"unused","severity":"warn","package":{"name":"foo"},"dependency":{"name_in_toml":"cfg-if","kind":"build"}},
+{"category":"ignored","severity":null,"package":{"name":"foo"},"dependency":{"name_in_toml":"matches","kind":"normal"}},
+{"category":"unused","severity":"deny","package":{"name":"bar"},"dependency":{"name_in_toml":"log","kind":"normal"}}
+]}
+"#;
+
+fn runner(prefix: &str) -> CargoResult<Runner> {
+	Ok(Runner::new(prefix)?
+		.file("old.json", OLD_JSON)?
+		.file("new.json", NEW_JSON)?
+		.arg("diff")
+		.arg("old.json")
+		.arg("new.json"))
+}
+
+#[test]
+fn diff() -> CargoResult<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_diff_diff")?.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"`foo`
+├─── appeared
+│    └─── "cfg-if" (build)
+├─── disappeared
+│    └─── "matches" (normal)
+└─── changed kind
+     └─── "maplit" (development → normal)
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn diff_json() -> CargoResult<()> {
+	let (code, stdout_masked) = runner("cargo_udeps_test_diff_diff_json")?
+		.arg("--output")
+		.arg("json")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		concat!(
+			r#"{"members":{"foo":{"appeared":[{"name":"cfg-if","kinds":["build"]}],"#,
+			r#""disappeared":[{"name":"matches","kinds":["normal"]}],"#,
+			r#""changed_kind":[{"name":"maplit","old_kinds":["development"],"new_kinds":["normal"]}]}}}"#,
+			"\n",
+		),
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn no_changes() -> CargoResult<()> {
+	let (code, stdout_masked) = Runner::new("cargo_udeps_test_diff_no_changes")?
+		.file("old.json", NEW_JSON)?
+		.arg("diff")
+		.arg("old.json")
+		.arg("old.json")
+		.run()?;
+	assert_eq!(0, code);
+	assert_eq!("No changes in unused dependencies.\n", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn analysis_args() -> CargoResult<()> {
+	let err = Runner::new("cargo_udeps_test_diff_analysis_args")?
+		.file("old.json", OLD_JSON)?
+		.file("new.json", NEW_JSON)?
+		.arg("--all-targets")
+		.arg("diff")
+		.arg("old.json")
+		.arg("new.json")
+		.run()
+		.expect_err("should fail");
+	let err = format!("{:#}", err);
+	assert!(err.contains("the argument '--all-targets' cannot be used with the `diff` subcommand"), "{}", err);
+	Ok(())
+}
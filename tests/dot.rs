@@ -0,0 +1,70 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "dot"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = ["if_chain"]
+
+[dependencies]
+bo = { package = "byteorder", version = "1.0.0" }
+if_chain = "1.0.0"
+unicode-normalization = "=0.1.22"
+
+[dev-dependencies]
+maplit = "1.0.2"
+
+[target.'cfg(any())'.dependencies]
+matches = "0.1.8"
+"#;
+
+static LIB_RS: &str = "use bo as _;\n";
+
+#[test]
+fn dot() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_dot")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("dot")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r##"digraph udeps {
+	rankdir=LR;
+	node [fontname="sans-serif"];
+	edge [fontname="sans-serif"];
+
+	"dot" [shape=box, style=bold];
+	"byteorder";
+	"if_chain";
+	"maplit" [style=filled, fillcolor="#f4a3a3"];
+	"matches";
+	"tinyvec" [style=filled, fillcolor="#f4a3a3"];
+	"unicode-normalization" [style=filled, fillcolor="#f4a3a3"];
+
+	"dot" -> "byteorder" [color=black, style=solid, tooltip="normal used", label="bo"];
+	"dot" -> "if_chain" [color=black, style=dashed, tooltip="normal ignored"];
+	"dot" -> "maplit" [color=blue, style=bold, tooltip="development unused"];
+	"dot" -> "matches" [color=black, style=dotted, tooltip="normal not evaluated"];
+	"dot" -> "unicode-normalization" [color=black, style=bold, tooltip="normal unused"];
+	"unicode-normalization" -> "tinyvec" [color="#f4a3a3"];
+}
+"##,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,42 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML :&str = r#"[workspace]
+
+[package]
+name = "exit_codes"
+version = "0.0.1"
+edition = "2018"
+publish = false
+"#;
+
+#[test]
+fn invalid_args() -> CargoResult<()> {
+	for arg in ["--bogus", "--severity=foo=bar"] {
+		let (code, stderr) = Runner::new("cargo_udeps_test_exit_codes_invalid_args")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg(arg)
+			.run_binary()?;
+		assert_eq!(2, code, "{}", arg);
+		assert!(stderr.starts_with("error: "), "{}", stderr);
+	}
+	Ok(())
+}
+
+#[test]
+fn build_failed() -> CargoResult<()> {
+	let (code, stderr) = Runner::new("cargo_udeps_test_exit_codes_build_failed")?
+		.cargo_toml(CARGO_TOML)?
+		.dir("./src")?
+		.file("./src/lib.rs", "fn f() -> u8 { \"\" }\n")?
+		.run_binary()?;
+	assert_eq!(101, code);
+	assert!(stderr.contains("could not compile `exit_codes`"), "{}", stderr);
+	Ok(())
+}
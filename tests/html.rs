@@ -0,0 +1,68 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "html"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = [{ name = "if_chain", reason = "used in <macros>" }]
+
+[dependencies]
+bo = { package = "byteorder", version = "1.0.0" }
+if_chain = "1.0.0"
+unicode-normalization = "=0.1.22"
+
+[target.'cfg(any())'.dependencies]
+matches = "0.1.8"
+"#;
+
+static LIB_RS: &str = "use bo as _;\n";
+
+#[test]
+fn html() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_html")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--output")
+			.arg("html")
+			.run()?;
+	assert_eq!(1, code);
+	assert!(stdout_masked.starts_with("<!DOCTYPE html>\n"), "{}", stdout_masked);
+	assert!(stdout_masked.ends_with("</html>\n"), "{}", stdout_masked);
+	// No external assets
+	for external in ["<link", "src=", "href=", "url("] {
+		assert!(!stdout_masked.contains(external), "{}", stdout_masked);
+	}
+	assert!(
+		stdout_masked.contains("<p>4 direct dependencies of 1 members: 1 ignored, 1 not evaluated, 1 unused, 1 used.</p>"),
+		"{}",
+		stdout_masked,
+	);
+	let rows = stdout_masked
+		.lines()
+		.filter(|line| line.starts_with("<tr class="))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![
+			r#"<tr class="used"><td>html</td><td>bo (byteorder)</td><td>normal</td><td>used</td><td></td><td>lib:html</td><td class="number" data-value="0"></td><td class="number" data-value="0"></td></tr>"#,
+			r#"<tr class="ignored"><td>html</td><td>if_chain</td><td>normal</td><td>ignored</td><td>used in &lt;macros&gt;</td><td></td><td class="number" data-value="0"></td><td class="number" data-value="0"></td></tr>"#,
+			r#"<tr class="not-evaluated"><td>html</td><td>matches</td><td>normal</td><td>not evaluated</td><td></td><td></td><td class="number" data-value="0"></td><td class="number" data-value="0"></td></tr>"#,
+		],
+		rows.iter().filter(|row| !row.contains("unicode-normalization")).copied().collect::<Vec<_>>(),
+	);
+	let unused = rows.iter().find(|row| row.contains("unicode-normalization")).unwrap();
+	assert!(unused.starts_with(r#"<tr class="unused">"#), "{}", unused);
+	assert!(unused.contains(r#"<td class="number" data-value="2" title="tinyvec@"#), "{}", unused);
+	Ok(())
+}
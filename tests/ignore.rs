@@ -122,3 +122,294 @@ matches = "0.1.8"
 	assert_eq!("All deps seem to have been used.\n", stdout_masked);
 	Ok(())
 }
+
+static SCOPED_CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "ignore-scoped"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[[bin]]
+name = "server"
+path = "src/server.rs"
+
+[[bin]]
+name = "client"
+path = "src/client.rs"
+
+[package.metadata.cargo-udeps.ignore]
+normal = [{ name = "maplit", target = "bin:server" }]
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+static MAIN_RS: &str = "fn main() {}\n";
+
+#[test]
+fn ignore_scoped_to_target() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_ignore_scoped_to_target")?
+			.cargo_toml(SCOPED_CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/server.rs", MAIN_RS)?
+			.file("./src/client.rs", MAIN_RS)?
+			.arg("--bin")
+			.arg("server")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	Ok(())
+}
+
+#[test]
+fn ignore_scoped_to_other_target() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_ignore_scoped_to_other_target")?
+			.cargo_toml(SCOPED_CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/server.rs", MAIN_RS)?
+			.file("./src/client.rs", MAIN_RS)?
+			.arg("--bins")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`ignore-scoped v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit"
+Note: These dependencies might be used by other targets.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn ignore_annotations() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "ignore-annotations"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0" # udeps: ignore(doc-tests)
+# udeps: ignore
+maplit = "1.0.2"
+matches = "0.1.8"
+
+[dev-dependencies.pretty_assertions] # udeps: ignore(used by the macros)
+version = "1.0"
+"#;
+
+	static LIB_RS: &str = "";
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_ignore_annotations")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("json-v2")
+			.run()?;
+	assert_eq!(1, code);
+	let report = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let findings = report["findings"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|finding| serde_json::json!([
+			finding["category"],
+			finding["dependency"]["kind"],
+			finding["dependency"]["name_in_toml"],
+			finding["ignore_reason"],
+		]))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![
+			serde_json::json!(["unused", "normal", "matches", null]),
+			serde_json::json!(["ignored", "normal", "if_chain", "doc-tests"]),
+			serde_json::json!(["ignored", "normal", "maplit", null]),
+			serde_json::json!(["ignored", "development", "pretty_assertions", "used by the macros"]),
+		],
+		findings,
+	);
+	Ok(())
+}
+
+#[test]
+fn ignore_scoped_to_target_kind() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "ignore-scoped"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[[bin]]
+name = "server"
+path = "src/server.rs"
+
+[[bin]]
+name = "client"
+path = "src/client.rs"
+
+[[example]]
+name = "example"
+path = "src/example.rs"
+
+[package.metadata.cargo-udeps.ignore]
+normal = [{ name = "maplit", target-kind = "bin" }]
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	let runner = |name, args :&[&str]| -> CargoResult<(i32, String)> {
+		let mut runner = Runner::new(name)?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/server.rs", MAIN_RS)?
+			.file("./src/client.rs", MAIN_RS)?
+			.file("./src/example.rs", MAIN_RS)?;
+		for arg in args {
+			runner = runner.arg(*arg);
+		}
+		runner.run()
+	};
+
+	let (code, stdout_masked) = runner("cargo_udeps_test_ignore_ignore_scoped_to_target_kind_bins", &["--bins"])?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+
+	let (code, stdout_masked) = runner("cargo_udeps_test_ignore_ignore_scoped_to_target_kind_examples", &["--bins", "--examples"])?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`ignore-scoped v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit"
+Note: These dependencies might be used by other targets.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn ignore_scoped_to_feature() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+
+[workspace.metadata.cargo-udeps.configs.server]
+features = ["server"]
+
+[workspace.metadata.cargo-udeps.configs.client]
+
+[package]
+name = "ignore-scoped"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = [{ name = "maplit", feature = "server" }]
+
+[features]
+server = []
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_ignore_scoped_to_feature")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--features")
+			.arg("server")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+
+	// Ignored in the `server` configuration, but that doesn't hide it being unused in `client`
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_ignore_scoped_to_feature_config_sets")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--lib")
+			.arg("--config-set")
+			.arg("all")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`ignore-scoped v0.0.0 (██████████)`
+└─── dependencies
+     └─── "maplit" (in client, server)
+Note: These dependencies might be used by other targets.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn ignore_scoped_to_cfg() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "ignore-scoped"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = [
+	{ name = "if_chain", cfg = "cfg(unix)" },
+	{ name = "maplit", cfg = "any(unix, windows)" },
+	{ name = "matches", cfg = "cfg(windows)" },
+]
+
+[dependencies]
+if_chain = "1.0.0"
+maplit = "1.0.2"
+matches = "0.1.8"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_ignore_ignore_scoped_to_cfg")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--lib")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`ignore-scoped v0.0.0 (██████████)`
+└─── dependencies
+     └─── "matches"
+Note: These dependencies might be used by other targets.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
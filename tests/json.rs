@@ -0,0 +1,251 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "json-report"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+bo = { package = "byteorder", version = "1.0.0", optional = true }
+
+[target.'cfg(any(unix, windows))'.dependencies]
+maplit = "1.0.2"
+"#;
+
+static LIB_RS: &str = "";
+
+#[test]
+fn legacy_report() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_json_legacy_report")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-targets")
+			.arg("--all-features")
+			.arg("--output")
+			.arg("json")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		concat!(
+			r#"{"success":false,"unused_deps":{"json-report 0.1.0 (path+file://██████████)":"#,
+			r#"{"manifest_path":"██████████/Cargo.toml","normal":["bo","maplit"],"development":[],"build":[]}},"#,
+			r#""note":"Note: They might be false-positive.\n      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.\n      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.\n"}"#,
+			"\n",
+		),
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn report() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_json_report")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--all-features")
+			.arg("--output")
+			.arg("json-v2")
+			.run()?;
+	assert_eq!(1, code);
+	let report = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!(2, report["version"]);
+	assert_eq!(env!("CARGO_PKG_VERSION"), report["cargo_udeps_version"]);
+	assert!(report["toolchain"].as_str().unwrap().starts_with("rustc "));
+	assert_eq!(false, report["success"]);
+	assert_eq!(true, report["options"]["all_features"]);
+	for finding in report["findings"].as_array().unwrap() {
+		let package = &finding["package"];
+		assert_eq!("json-report", package["name"]);
+		assert_eq!("0.1.0", package["version"]);
+		assert!(package["id"].as_str().unwrap().ends_with("#json-report@0.1.0"));
+		assert!(package["source"].as_str().unwrap().starts_with("path+file://"));
+		assert!(package["manifest_path"].as_str().unwrap().ends_with("Cargo.toml"));
+	}
+	let findings = report["findings"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|finding| json!([finding["category"], finding["severity"], finding["dependency"]]))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		vec![
+			json!(["unused", "deny", {
+				"name_in_toml": "bo",
+				"package_name": "byteorder",
+				"kind": "normal",
+				"optional": true,
+				"target": null,
+				"location": { "line": 10, "column": 1 },
+			}]),
+			json!(["unused", "deny", {
+				"name_in_toml": "maplit",
+				"package_name": "maplit",
+				"kind": "normal",
+				"optional": false,
+				"target": "cfg(any(unix, windows))",
+				"location": { "line": 13, "column": 1 },
+			}]),
+		],
+		findings,
+	);
+	assert_eq!(
+		vec!["other-targets", "enable-all-targets", "false-positives"],
+		report["notes"]
+			.as_array()
+			.unwrap()
+			.iter()
+			.map(|note| note["code"].as_str().unwrap())
+			.collect::<Vec<_>>(),
+	);
+	Ok(())
+}
+
+#[test]
+fn message_format_json() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_json_message_format_json")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--message-format")
+			.arg("json")
+			.arg("--output")
+			.arg("json-v2")
+			.run()?;
+	assert_eq!(1, code);
+	let messages = stdout_masked
+		.lines()
+		.map(serde_json::from_str::<serde_json::Value>)
+		.collect::<Result<Vec<_>, _>>()?;
+	assert_eq!(2, messages.len());
+	assert_eq!("cargo-udeps-unused-dependency", messages[0]["reason"]);
+	assert!(messages[0]["package_id"].as_str().unwrap().ends_with("#json-report@0.1.0"));
+	assert_eq!("maplit", messages[0]["dependency"]["name_in_toml"]);
+	assert_eq!("deny", messages[0]["severity"]);
+	// The report follows the messages
+	assert_eq!(2, messages[1]["version"]);
+	Ok(())
+}
+
+#[test]
+fn message_format_json_members() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+	static A_CARGO_TOML: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+"#;
+
+	static B_CARGO_TOML: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+byteorder = "1.0.0"
+maplit = "1.0.2"
+"#;
+
+	static BASELINE: &str = r#"{ "version": 1, "unused": { "b": { "normal": ["byteorder"] } } }"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_json_message_format_json_members")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./a/src")?
+			.file("./a/Cargo.toml", A_CARGO_TOML)?
+			.file("./a/src/lib.rs", LIB_RS)?
+			.dir("./b/src")?
+			.file("./b/Cargo.toml", B_CARGO_TOML)?
+			.file("./b/src/lib.rs", LIB_RS)?
+			.file("./baseline.json", BASELINE)?
+			.arg("--baseline")
+			.arg("baseline.json")
+			.arg("--message-format")
+			.arg("json")
+			.arg("--output")
+			.arg("json-v2")
+			.run()?;
+	assert_eq!(1, code);
+	let messages = stdout_masked
+		.lines()
+		.map(serde_json::from_str::<serde_json::Value>)
+		.collect::<Result<Vec<_>, _>>()?;
+	// One message for each member, the baselined dependency has none
+	let dependencies = messages[..messages.len() - 1]
+		.iter()
+		.map(|message| (message["package"]["name"].clone(), message["dependency"]["name_in_toml"].clone()))
+		.collect::<Vec<_>>();
+	assert_eq!(vec![(json!("a"), json!("maplit")), (json!("b"), json!("maplit"))], dependencies);
+	assert_eq!(2, messages[messages.len() - 1]["version"]);
+	Ok(())
+}
+
+#[test]
+fn savings() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "json-savings"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+unicode-normalization = "=0.1.22"
+"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_json_savings")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--output")
+			.arg("json-v2")
+			.arg("--compile-times")
+			.run()?;
+	assert_eq!(1, code);
+	let report = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	let findings = report["findings"].as_array().unwrap();
+	// Sorted by the number of removed packages
+	assert_eq!(
+		vec!["unicode-normalization", "maplit"],
+		findings
+			.iter()
+			.map(|finding| finding["dependency"]["name_in_toml"].as_str().unwrap())
+			.collect::<Vec<_>>(),
+	);
+	let savings = &findings[0]["savings"];
+	assert_eq!(2, savings["removed_packages"]);
+	let packages = savings["packages"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|package| package.as_str().unwrap().split('@').next().unwrap())
+		.collect::<Vec<_>>();
+	assert_eq!(vec!["tinyvec", "unicode-normalization"], packages);
+	assert!(savings["compile_time_secs"].as_f64().unwrap() > 0.0);
+	assert_eq!(1, findings[1]["savings"]["removed_packages"]);
+	Ok(())
+}
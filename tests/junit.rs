@@ -0,0 +1,109 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "junit"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+byteorder = "1.0.0" # udeps: ignore(only used in doc-tests)
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+static LIB_RS: &str = "";
+static TEST_RS: &str = "#[test]\nfn test() { let _ = maplit::hashset![1]; }\n";
+
+#[test]
+fn junit() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_junit")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.dir("./tests")?
+			.file("./tests/test.rs", TEST_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("junit")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="cargo-udeps" tests="3" failures="1" skipped="1">
+  <testsuite name="junit v0.0.1 (██████████)" tests="3" failures="1" errors="0" skipped="1">
+    <testcase name="byteorder" classname="junit.dependencies">
+      <skipped message="only used in doc-tests"/>
+    </testcase>
+    <testcase name="if_chain" classname="junit.dependencies">
+      <failure message="unused dependency `if_chain`" type="unused-dependency">Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+</failure>
+    </testcase>
+    <testcase name="maplit" classname="junit.dev-dependencies"/>
+  </testsuite>
+</testsuites>
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn junit_baseline() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "junit"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[target.'cfg(any())'.dependencies]
+matches = "0.1.8"
+"#;
+	static BASELINE: &str = r#"{"version":1,"unused":{"junit":{"normal":["if_chain"]}}}"#;
+
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_junit_baseline")?
+			.cargo_toml(CARGO_TOML)?
+			.file("udeps-baseline.json", BASELINE)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.arg("--baseline")
+			.arg("udeps-baseline.json")
+			.arg("--output")
+			.arg("junit")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="cargo-udeps" tests="2" failures="0" skipped="2">
+  <testsuite name="junit v0.0.1 (██████████)" tests="2" failures="0" errors="0" skipped="2">
+    <testcase name="if_chain" classname="junit.dependencies">
+      <skipped message="unused, accepted by the baseline"/>
+    </testcase>
+    <testcase name="matches" classname="junit.dependencies">
+      <skipped message="not evaluated, for example because it is for another platform"/>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
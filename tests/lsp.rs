@@ -0,0 +1,99 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "lsp"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+bo = { package = "byteorder", version = "1.0.0" }
+"#;
+
+static LIB_RS: &str = "use bo as _;\n";
+
+#[test]
+fn lsp() -> CargoResult<()> {
+	let messages =
+		Runner::new("cargo_udeps_test_lsp")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.run_lsp(&[
+				json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "capabilities": {} } }),
+				json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+				json!({
+					"jsonrpc": "2.0",
+					"method": "textDocument/didOpen",
+					"params": {
+						"textDocument": { "uri": "$ROOT/Cargo.toml", "languageId": "toml", "version": 1, "text": CARGO_TOML },
+					},
+				}),
+				json!({
+					"jsonrpc": "2.0",
+					"id": 2,
+					"method": "textDocument/codeAction",
+					"params": {
+						"textDocument": { "uri": "$ROOT/Cargo.toml" },
+						"range": { "start": { "line": 9, "character": 0 }, "end": { "line": 9, "character": 0 } },
+						"context": { "diagnostics": [] },
+					},
+				}),
+				json!({
+					"jsonrpc": "2.0",
+					"method": "textDocument/didSave",
+					"params": { "textDocument": { "uri": "$ROOT/src/lib.rs" } },
+				}),
+				json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" }),
+				json!({ "jsonrpc": "2.0", "method": "exit" }),
+			])?;
+
+	let diagnostic = json!({
+		"range": { "start": { "line": 9, "character": 0 }, "end": { "line": 9, "character": 8 } },
+		"severity": 1,
+		"source": "cargo-udeps",
+		"message": "unused dependency `if_chain`",
+	});
+	let diagnostics = json!({
+		"jsonrpc": "2.0",
+		"method": "textDocument/publishDiagnostics",
+		"params": { "uri": "$ROOT/Cargo.toml", "diagnostics": [diagnostic] },
+	});
+	assert_eq!(5, messages.len());
+	assert_eq!(json!({ "codeActionProvider": { "codeActionKinds": ["quickfix"] }, "textDocumentSync": { "change": 1, "openClose": true, "save": true } }), messages[0]["result"]["capabilities"]);
+	assert_eq!(diagnostics, messages[1]);
+	assert_eq!(
+		json!({
+			"jsonrpc": "2.0",
+			"id": 2,
+			"result": [{
+				"title": "Remove unused dependency `if_chain`",
+				"kind": "quickfix",
+				"diagnostics": [diagnostic],
+				"isPreferred": true,
+				"edit": {
+					"changes": {
+						"$ROOT/Cargo.toml": [{
+							"range": { "start": { "line": 9, "character": 0 }, "end": { "line": 10, "character": 0 } },
+							"newText": "",
+						}],
+					},
+				},
+			}],
+		}),
+		messages[2],
+	);
+	// Nothing changed, so the results of the first analysis are published again
+	assert_eq!(diagnostics, messages[3]);
+	assert_eq!(json!({ "jsonrpc": "2.0", "id": 3, "result": null }), messages[4]);
+	Ok(())
+}
@@ -0,0 +1,85 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+members = ["foo"]
+
+[workspace.dependencies]
+maplit = "1.0.2"
+"#;
+
+static FOO_CARGO_TOML: &str = r#"[package]
+name = "foo"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+log = "0.4" # udeps: ignore(used | unused)
+
+[dev-dependencies]
+maplit = { workspace = true }
+
+[build-dependencies.matches]
+version = "0.1.8"
+"#;
+
+static LIB_RS: &str = "";
+static BUILD_RS: &str = "fn main() {}\n";
+
+#[test]
+fn markdown() -> CargoResult<()> {
+	let run = || -> CargoResult<_> {
+		Runner::new("cargo_udeps_test_markdown")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./foo/src")?
+			.file("./foo/Cargo.toml", FOO_CARGO_TOML)?
+			.file("./foo/src/lib.rs", LIB_RS)?
+			.file("./foo/build.rs", BUILD_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("markdown")
+			.arg("--severity")
+			.arg("development=warn")
+			.run()
+	};
+	let (code, stdout_masked) = run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"**cargo-udeps**: 2 unused dependencies, 1 warning, 1 ignored
+
+#### `foo v0.0.1`
+
+| Dependency | Kind | Level | Manifest | Action |
+|---|---|---|---|---|
+| `if_chain` | dependencies | error | [foo/Cargo.toml:8](foo/Cargo.toml#L8) | remove this line |
+| `maplit` | dev-dependencies | warning | [foo/Cargo.toml:12](foo/Cargo.toml#L12) | remove this line, and from `[workspace.dependencies]` if no other member uses it |
+| `matches` | build-dependencies | error | [foo/Cargo.toml:14](foo/Cargo.toml#L14) | remove the `matches` table |
+
+<details>
+<summary>Notes</summary>
+
+- They might be false-positive. For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests. To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+
+</details>
+
+<details>
+<summary>Ignored dependencies (1)</summary>
+
+| Package | Dependency | Kind | Reason |
+|---|---|---|---|
+| `foo` | `log` | dependencies | used \| unused |
+
+</details>
+"#,
+		stdout_masked,
+	);
+	// Repeated runs, in other directories, produce the same comment
+	assert_eq!((code, stdout_masked), run()?);
+	Ok(())
+}
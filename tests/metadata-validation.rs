@@ -0,0 +1,116 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static LIB_RS: &str = "";
+
+fn error_of(prefix: &str, cargo_toml: &str) -> CargoResult<String> {
+	let err = Runner::new(prefix)?
+		.cargo_toml(cargo_toml)?
+		.dir("./src")?
+		.file("./src/lib.rs", LIB_RS)?
+		.run()
+		.expect_err("should fail");
+	Ok(format!("{:#}", err))
+}
+
+#[test]
+fn unknown_package_metadata_key() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "unknown-package-metadata-key"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignroe]
+normal = ["if_chain"]
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+	let err = error_of("cargo_udeps_test_metadata_validation_unknown_package_metadata_key", CARGO_TOML)?;
+	assert!(err.contains("could not parse `package.metadata.cargo-udeps` in `"), "{}", err);
+	assert!(err.contains("Cargo.toml`"), "{}", err);
+	assert!(err.contains("unknown field `ignroe`, expected `ignore`"), "{}", err);
+	Ok(())
+}
+
+#[test]
+fn unknown_ignore_kind() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "unknown-ignore-kind"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+dev = ["if_chain"]
+
+[dependencies]
+if_chain = "1.0.0"
+"#;
+
+	let err = error_of("cargo_udeps_test_metadata_validation_unknown_ignore_kind", CARGO_TOML)?;
+	assert!(err.contains("unknown field `dev`, expected one of `normal`, `development`, `build`"), "{}", err);
+	Ok(())
+}
+
+#[test]
+fn unknown_workspace_metadata_key() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+
+[workspace.metadata.cargo-udeps]
+all-target = true
+
+[package]
+name = "unknown-workspace-metadata-key"
+version = "0.0.0"
+edition = "2018"
+publish = false
+"#;
+
+	let err = error_of("cargo_udeps_test_metadata_validation_unknown_workspace_metadata_key", CARGO_TOML)?;
+	assert!(err.contains("unknown key `all-target` in `workspace.metadata.cargo-udeps` in `"), "{}", err);
+	Ok(())
+}
+
+#[test]
+fn ignore_of_other_kind() -> CargoResult<()> {
+	static CARGO_TOML: &str = r#"[workspace]
+[package]
+name = "ignore-of-other-kind"
+version = "0.0.0"
+edition = "2018"
+publish = false
+
+[package.metadata.cargo-udeps.ignore]
+normal = ["maplit"]
+
+[dev-dependencies]
+maplit = "1.0.2"
+"#;
+
+	let (code, stdout_masked, stderr_masked) =
+		Runner::new("cargo_udeps_test_metadata_validation_ignore_of_other_kind")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.run_with_stderr()?;
+	assert_eq!(0, code);
+	assert_eq!("All deps seem to have been used.\n", stdout_masked);
+	assert!(
+		stderr_masked.contains(
+			"warning: `package.metadata.cargo-udeps` of `ignore-of-other-kind` ignores `maplit` as a normal dependency, \
+			but it isn't declared as one (it is a development dependency)",
+		),
+		"{}",
+		stderr_masked,
+	);
+	Ok(())
+}
@@ -2,7 +2,9 @@
 
 use std::ffi::OsString;
 use std::path::PathBuf;
-use std::process::{Command, Output};
+use std::io::Write as _;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
 use std::{env, fs, io, str};
 
 use anyhow::Context;
@@ -34,6 +36,8 @@ fn set_rustc_env() -> CargoResult<()> {
 
 pub(crate) struct Runner {
 	cwd :TempDir,
+	/// Where `cargo udeps` is run, relative to `cwd`
+	current_dir :PathBuf,
 	cargo_home :PathBuf,
 	args :Vec<OsString>,
 }
@@ -49,6 +53,7 @@ impl Runner {
 		let args = vec!["".into(), "udeps".into()];
 		Ok(Self {
 			cwd,
+			current_dir : PathBuf::new(),
 			cargo_home,
 			args,
 		})
@@ -64,27 +69,160 @@ impl Runner {
 		Ok(self)
 	}
 
+	/// Writes a file that can be executed, like a script.
+	#[cfg(unix)]
+	pub(crate) fn executable(self, file_name :&str, content :&str) -> io::Result<Self> {
+		use std::os::unix::fs::PermissionsExt;
+
+		let path = self.cwd.path().join(file_name);
+		fs::write(&path, content)?;
+		fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+		Ok(self)
+	}
+
 	pub(crate) fn dir(self, name :&str) -> io::Result<Self> {
 		let path = self.cwd.path().join(name);
 		fs::create_dir_all(path)?;
 		Ok(self)
 	}
 
+	/// Runs `cargo udeps` in the subdirectory `dir` instead of the temporary directory itself.
+	pub(crate) fn current_dir(mut self, dir :&str) -> Self {
+		self.current_dir = dir.into();
+		self
+	}
+
 	pub(crate) fn arg<S: Into<OsString>>(mut self, arg: S) -> Self {
 		self.args.push(arg.into());
 		self
 	}
 
 	pub(crate) fn run(self) -> CargoResult<(i32, String)> {
+		let (code, stdout, _) = self.run_with_stderr()?;
+		Ok((code, stdout))
+	}
+
+	/// Like `run`, but also returns what was written to stderr,
+	/// unless `UDEPS_VERBOSE_TEST` is set.
+	pub(crate) fn run_with_stderr(self) -> CargoResult<(i32, String, String)> {
+		self.execute()
+	}
+
+	/// Like `run`, but also returns the content of a file afterwards.
+	pub(crate) fn run_and_read(self, file_name :&str) -> CargoResult<(i32, String, String)> {
+		let (code, stdout, _) = self.execute()?;
+		let content = fs::read_to_string(self.cwd.path().join(file_name))?;
+		Ok((code, stdout, content))
+	}
+
+	/// Runs the library API in the directory instead of the command line.
+	pub(crate) fn run_api(self, udeps :cargo_udeps::Udeps) -> CargoResult<cargo_udeps::Report> {
+		let mut config = cargo::util::context::GlobalContext::new(Shell::from_write(Box::new(io::sink())),
+			self.cwd.path().to_owned(), self.cargo_home.clone());
+		udeps.manifest_path(self.cwd.path().join("Cargo.toml")).run_with_context(&mut config)
+	}
+
+	/// Runs `cargo udeps lsp` in the directory, sending `messages` and returning what the server sent.
+	///
+	/// `$ROOT` in the messages is replaced with the URL of the directory, and the other way around.
+	pub(crate) fn run_lsp(self, messages :&[serde_json::Value]) -> CargoResult<Vec<serde_json::Value>> {
+		let root = url::Url::from_directory_path(self.cwd.path()).unwrap().to_string();
+		let root = root.trim_end_matches('/');
+		let mut input = Vec::new();
+		for message in messages {
+			let body = message.to_string().replace("$ROOT", root);
+			write!(input, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+		}
+		let mut child = Command::new(env!("CARGO_BIN_EXE_cargo-udeps"))
+			.args(["udeps", "lsp"])
+			.current_dir(self.cwd.path())
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(if env::var("UDEPS_VERBOSE_TEST").is_ok() { Stdio::inherit() } else { Stdio::null() })
+			.spawn()?;
+		child.stdin.take().unwrap().write_all(&input)?;
+		let Output { status, stdout, .. } = child.wait_with_output()?;
+		if !status.success() {
+			return Err(anyhow::anyhow!("{}", status)).with_context(|| "the language server failed");
+		}
+		let stdout = str::from_utf8(&stdout)?.replace(root, "$ROOT");
+		stdout
+			.split("Content-Length: ")
+			.skip(1)
+			.map(|message| {
+				let (_, body) = message.split_once("\r\n\r\n").with_context(|| "missing header end")?;
+				Ok(serde_json::from_str(body)?)
+			})
+			.collect()
+	}
+
+	/// Runs the `cargo-udeps` binary in the directory, returning its exit code and stderr.
+	pub(crate) fn run_binary(self) -> CargoResult<(i32, String)> {
+		let Output { status, stderr, .. } = Command::new(env!("CARGO_BIN_EXE_cargo-udeps"))
+			.args(&self.args[1..])
+			.current_dir(self.cwd.path())
+			.stdin(Stdio::null())
+			.output()?;
+		let code = status.code().with_context(|| "terminated by a signal")?;
+		let stderr = str::from_utf8(&stderr)?.replace(&*self.cwd.path().to_string_lossy(), "██████████");
+		Ok((code, stderr))
+	}
+
+	/// Runs `cargo udeps --watch` in the directory, writes `files` after the first analysis,
+	/// and returns stdout after the second one.
+	pub(crate) fn run_watch(self, files :&[(&str, &str)]) -> CargoResult<String> {
+		let mut child = Command::new(env!("CARGO_BIN_EXE_cargo-udeps"))
+			.args(&self.args[1..])
+			.arg("--watch")
+			.current_dir(self.cwd.path())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()?;
+		let mut stdout = child.stdout.take().unwrap();
+		let stdout = std::thread::spawn(move || {
+			let mut buf = String::new();
+			io::Read::read_to_string(&mut stdout, &mut buf).map(|_| buf)
+		});
+		let (watching, analyses) = std::sync::mpsc::channel();
+		let stderr = io::BufReader::new(child.stderr.take().unwrap());
+		std::thread::spawn(move || {
+			for line in io::BufRead::lines(stderr).map_while(Result::ok) {
+				if env::var("UDEPS_VERBOSE_TEST").is_ok() {
+					eprintln!("{}", line);
+				}
+				if line.trim_start().starts_with("Watching") && watching.send(()).is_err() {
+					break;
+				}
+			}
+		});
+
+		let timeout = std::time::Duration::from_secs(600);
+		let result = analyses.recv_timeout(timeout)
+			.with_context(|| "the first analysis didn't finish")
+			.and_then(|()| {
+				for (file_name, content) in files {
+					fs::write(self.cwd.path().join(file_name), content)?;
+				}
+				analyses.recv_timeout(timeout).with_context(|| "the second analysis didn't finish")
+			});
+		child.kill()?;
+		child.wait()?;
+		result?;
+		let stdout = stdout.join().unwrap()?;
+		Ok(stdout.replace(&*self.cwd.path().to_string_lossy(), "██████████"))
+	}
+
+	fn execute(&self) -> CargoResult<(i32, String, String)> {
 		let mut stdout = vec![];
+		let stderr_buf = SharedBuf::default();
 		let stderr = if std::env::var("UDEPS_VERBOSE_TEST").is_ok() {
 			Shell::new()
 		} else {
 			eprintln!("Please set the UDEPS_VERBOSE_TEST environment variable to enable more verbose logging");
-			Shell::from_write(Box::new(vec![]))
+			Shell::from_write(Box::new(stderr_buf.clone()))
 		};
 		let mut config = cargo::util::context::GlobalContext::new(stderr,
-			self.cwd.path().to_owned(), self.cargo_home.clone());
+			self.cwd.path().join(&self.current_dir), self.cargo_home.clone());
 		let code = match cargo_udeps::run(self.args.clone(), &mut config, &mut stdout) {
 			Ok(()) => 0,
 			Err(CliError {
@@ -96,6 +234,20 @@ impl Runner {
 		};
 		let cwd_lossy = self.cwd.path().to_string_lossy();
 		let stdout = str::from_utf8(&stdout)?.replace(&*cwd_lossy, "██████████");
-		Ok((code, stdout))
+		let stderr = str::from_utf8(&stderr_buf.0.lock().unwrap())?.replace(&*cwd_lossy, "██████████");
+		Ok((code, stdout, stderr))
+	}
+}
+
+#[derive(Clone, Default)]
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for SharedBuf {
+	fn write(&mut self, buf :&[u8]) -> io::Result<usize> {
+		self.0.lock().unwrap().write(buf)
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
 	}
 }
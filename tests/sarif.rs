@@ -0,0 +1,74 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "sarif"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[dev-dependencies]
+  maplit = "1.0.2"
+
+[build-dependencies.matches]
+version = "0.1.8"
+"#;
+
+static LIB_RS: &str = "";
+static BUILD_RS: &str = "fn main() {}\n";
+
+#[test]
+fn sarif() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_sarif")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.file("./build.rs", BUILD_RS)?
+			.arg("--all-targets")
+			.arg("--output")
+			.arg("sarif")
+			.arg("--severity")
+			.arg("development=warn")
+			.run()?;
+	assert_eq!(1, code);
+	let sarif = serde_json::from_str::<serde_json::Value>(&stdout_masked)?;
+	assert_eq!("2.1.0", sarif["version"]);
+	let results = sarif["runs"][0]["results"]
+		.as_array()
+		.unwrap()
+		.iter()
+		.map(|result| json!([
+			result["ruleId"],
+			result["level"],
+			result["locations"][0]["physicalLocation"]["artifactLocation"],
+			result["locations"][0]["physicalLocation"]["region"],
+		]))
+		.collect::<Vec<_>>();
+	let location = |line, column, length| json!({
+		"startLine": line,
+		"startColumn": column,
+		"endLine": line,
+		"endColumn": column + length,
+	});
+	let manifest = json!({ "uri": "Cargo.toml", "uriBaseId": "%SRCROOT%" });
+	assert_eq!(
+		vec![
+			json!(["unused-dependency", "error", manifest, location(10, 1, 8)]),
+			json!(["unused-dev-dependency", "warning", manifest, location(13, 3, 6)]),
+			json!(["unused-build-dependency", "error", manifest, location(15, 21, 7)]),
+		],
+		results,
+	);
+	Ok(())
+}
@@ -0,0 +1,70 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "savings"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+maplit = "1.0.2"
+unicode-normalization = "=0.1.22"
+"#;
+
+#[test]
+fn sorted_by_savings() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_savings_sorted_by_savings")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.arg("--compile-times")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`savings v0.1.0 (██████████)`
+└─── dependencies
+     ├─── "unicode-normalization" (removes 2 packages, █s of compile time)
+     └─── "maplit" (removes 1 package, █s of compile time)
+Note: These dependencies might be used by other targets.
+      To find dependencies that are not used by any target, enable `--all-targets`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		mask_compile_times(&stdout_masked),
+	);
+	Ok(())
+}
+
+#[test]
+fn hidden_by_default() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_savings_hidden_by_default")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", "")?
+			.run()?;
+	assert_eq!(1, code);
+	assert!(stdout_masked.contains("     ├─── \"maplit\"\n     └─── \"unicode-normalization\"\n"), "{}", stdout_masked);
+	Ok(())
+}
+
+/// Replaces the compile times, which differ from run to run, with `█`.
+fn mask_compile_times(stdout :&str) -> String {
+	stdout
+		.lines()
+		.map(|line| match line.strip_suffix("s of compile time)").and_then(|line| line.rsplit_once(", ")) {
+			Some((line, _)) => format!("{}, █s of compile time)\n", line),
+			None => format!("{}\n", line),
+		})
+		.collect()
+}
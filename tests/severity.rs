@@ -0,0 +1,111 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+
+[package]
+name = "severity"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[dev-dependencies]
+maplit = "1.0.2"
+
+[build-dependencies]
+matches = "0.1.8"
+"#;
+
+static CARGO_TOML_METADATA: &str = r#"[workspace]
+
+[workspace.metadata.cargo-udeps.severity]
+unused = "warn"
+normal = "deny"
+
+[package]
+name = "severity"
+version = "0.0.1"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+
+[dev-dependencies]
+maplit = "1.0.2"
+
+[build-dependencies]
+matches = "0.1.8"
+"#;
+
+static LIB_RS: &str = "";
+static BUILD_RS: &str = "fn main() {}\n";
+
+#[test]
+fn only_warn() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_severity_only_warn")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.file("./build.rs", BUILD_RS)?
+			.arg("--all-targets")
+			.arg("--severity")
+			.arg("unused=warn")
+			.arg("--severity")
+			.arg("build=allow")
+			.run()?;
+	assert_eq!(0, code);
+	assert_eq!(
+		r#"unused dependencies (warning):
+`severity v0.0.1 (██████████)`
+├─── dependencies
+│    └─── "if_chain"
+└─── dev-dependencies
+     └─── "maplit"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
+
+#[test]
+fn workspace_metadata() -> CargoResult<()> {
+	let (code, stdout_masked) =
+		Runner::new("cargo_udeps_test_severity_workspace_metadata")?
+			.cargo_toml(CARGO_TOML_METADATA)?
+			.dir("./src")?
+			.file("./src/lib.rs", LIB_RS)?
+			.file("./build.rs", BUILD_RS)?
+			.arg("--all-targets")
+			.run()?;
+	assert_eq!(1, code);
+	assert_eq!(
+		r#"unused dependencies:
+`severity v0.0.1 (██████████)`
+└─── dependencies
+     └─── "if_chain"
+unused dependencies (warning):
+`severity v0.0.1 (██████████)`
+├─── dev-dependencies
+│    └─── "maplit"
+└─── build-dependencies
+     └─── "matches"
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#,
+		stdout_masked,
+	);
+	Ok(())
+}
@@ -0,0 +1,75 @@
+mod runner;
+
+use cargo::CargoResult;
+use pretty_assertions::assert_eq;
+
+use crate::runner::Runner;
+
+static CARGO_TOML: &str = r#"[workspace]
+members = ["a", "b"]
+"#;
+
+static A_CARGO_TOML: &str = r#"[package]
+name = "a"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+if_chain = "1.0.0"
+matches = "0.1.8"
+"#;
+
+static B_CARGO_TOML: &str = r#"[package]
+name = "b"
+version = "0.1.0"
+edition = "2018"
+publish = false
+
+[dependencies]
+matches = "0.1.8"
+"#;
+
+static NOTES: &str = r#"Note: These dependencies might be used by other targets.
+      To find dependencies that are not used by any target, enable `--all-targets`.
+Note: They might be false-positive.
+      For example, `cargo-udeps` cannot detect usage of crates that are only used in doc-tests.
+      To ignore some dependencies, write `package.metadata.cargo-udeps.ignore` in Cargo.toml.
+"#;
+
+#[test]
+fn watch() -> CargoResult<()> {
+	let stdout_masked =
+		Runner::new("cargo_udeps_test_watch")?
+			.cargo_toml(CARGO_TOML)?
+			.dir("./a/src")?
+			.dir("./b/src")?
+			.file("./a/Cargo.toml", A_CARGO_TOML)?
+			.file("./a/src/lib.rs", "use matches as _;\n")?
+			.file("./b/Cargo.toml", B_CARGO_TOML)?
+			.file("./b/src/lib.rs", "")?
+			.run_watch(&[("./a/src/lib.rs", "use if_chain as _;\nuse matches as _;\n")])?;
+	assert_eq!(
+		format!(
+			r#"unused dependencies:
+`a v0.1.0 (██████████/a)`
+└─── dependencies
+     └─── "if_chain"
+`b v0.1.0 (██████████/b)`
+└─── dependencies
+     └─── "matches"
+{notes}unused dependencies:
+`b v0.1.0 (██████████/b)`
+└─── dependencies
+     └─── "matches"
+{notes}Changes since the previous run:
+`a`
+└─── disappeared
+     └─── "if_chain" (normal)
+"#,
+			notes = NOTES,
+		),
+		stdout_masked,
+	);
+	Ok(())
+}